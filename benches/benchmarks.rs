@@ -10,6 +10,7 @@ use dream::*;
 
 define_index!(test_index(
     public
+    dense_and_sparse
 ) {
 } use {
 });
@@ -21,6 +22,7 @@ struct Config {
     total_tags_count: usize,
     object_tags_count: usize,
     objects_count: usize,
+    sparse_tag_objects_count: usize,
     benchmark_in_memory: bool,
     benchmark_on_disk: bool,
 }
@@ -54,6 +56,25 @@ fn criterion_benchmark(bencher_context: &mut Criterion) {
         })
         .unwrap();
 
+    // separate schema without rng, so that data and tags picked for public schema benchmarks stay the same
+    let dense_tag = Id([0xdd; 16]);
+    let sparse_tag = Id([0x55; 16]);
+    let sparse_tag_step = (config.objects_count / config.sparse_tag_objects_count.max(1)).max(1);
+    index
+        .lock_all_and_write(|transaction| {
+            for object_index in 0..config.objects_count {
+                let mut object_value = [0u8; 16];
+                object_value[..8].copy_from_slice(&(object_index as u64).to_be_bytes());
+                let mut tags = vec![dense_tag.clone()];
+                if object_index % sparse_tag_step == 0 {
+                    tags.push(sparse_tag.clone());
+                }
+                transaction.dense_and_sparse_insert(&Id(object_value), &tags)?;
+            }
+            Ok(())
+        })
+        .unwrap();
+
     if config.benchmark_in_memory {
         for search_tags_count in 1..=4 {
             bencher_context.bench_function(
@@ -70,7 +91,7 @@ fn criterion_benchmark(bencher_context: &mut Criterion) {
                         |present_tags| {
                             index.lock_all_writes_and_read(|transaction| {
                                 transaction
-                                    .public_search(&present_tags, &[], None)?
+                                    .public_search(&present_tags, &[], None, Direction::Ascending)?
                                     .collect::<Vec<_>>()?;
                                 Ok(())
                             })
@@ -80,6 +101,24 @@ fn criterion_benchmark(bencher_context: &mut Criterion) {
                 },
             );
         }
+        bencher_context.bench_function(
+            "in-memory: searching all objects by dense and sparse tags",
+            |bencher| {
+                bencher.iter(|| {
+                    index.lock_all_writes_and_read(|transaction| {
+                        transaction
+                            .dense_and_sparse_search(
+                                &[dense_tag.clone(), sparse_tag.clone()],
+                                &[],
+                                None,
+                                Direction::Ascending,
+                            )?
+                            .collect::<Vec<_>>()?;
+                        Ok(())
+                    })
+                });
+            },
+        );
     }
     if config.benchmark_on_disk {
         index.database.lock_all_and_checkpoint(false).unwrap();
@@ -98,7 +137,7 @@ fn criterion_benchmark(bencher_context: &mut Criterion) {
                         |present_tags| {
                             index.lock_all_writes_and_read(|transaction| {
                                 transaction
-                                    .public_search(&present_tags, &[], None)?
+                                    .public_search(&present_tags, &[], None, Direction::Ascending)?
                                     .collect::<Vec<_>>()?;
                                 Ok(())
                             })
@@ -108,6 +147,24 @@ fn criterion_benchmark(bencher_context: &mut Criterion) {
                 },
             );
         }
+        bencher_context.bench_function(
+            "on-disk: searching all objects by dense and sparse tags",
+            |bencher| {
+                bencher.iter(|| {
+                    index.lock_all_writes_and_read(|transaction| {
+                        transaction
+                            .dense_and_sparse_search(
+                                &[dense_tag.clone(), sparse_tag.clone()],
+                                &[],
+                                None,
+                                Direction::Ascending,
+                            )?
+                            .collect::<Vec<_>>()?;
+                        Ok(())
+                    })
+                });
+            },
+        );
     }
 }
 
@@ -0,0 +1,154 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::{Id, bincode};
+
+/// Attribute value stored as 8 bytes which compare in the same order as values themselves
+pub trait AttributeValue: Sized {
+    fn to_ordered_bytes(&self) -> [u8; 8];
+    fn from_ordered_bytes(bytes: [u8; 8]) -> Self;
+}
+
+impl AttributeValue for u64 {
+    fn to_ordered_bytes(&self) -> [u8; 8] {
+        self.to_be_bytes()
+    }
+
+    fn from_ordered_bytes(bytes: [u8; 8]) -> Self {
+        Self::from_be_bytes(bytes)
+    }
+}
+
+// sign bit is flipped so that negative values precede positive ones
+impl AttributeValue for i64 {
+    fn to_ordered_bytes(&self) -> [u8; 8] {
+        ((*self as u64) ^ (1 << 63)).to_be_bytes()
+    }
+
+    fn from_ordered_bytes(bytes: [u8; 8]) -> Self {
+        (u64::from_be_bytes(bytes) ^ (1 << 63)) as i64
+    }
+}
+
+// negative values have all bits flipped to reverse their order, positive ones only sign bit, which gives IEEE 754 total order:
+// NaNs with sign bit set precede negative infinity, -0.0 precedes 0.0, and NaNs without it (as f64::NAN) follow positive infinity
+impl AttributeValue for f64 {
+    fn to_ordered_bytes(&self) -> [u8; 8] {
+        let bits = self.to_bits();
+        if bits >> 63 == 1 {
+            !bits
+        } else {
+            bits ^ (1 << 63)
+        }
+        .to_be_bytes()
+    }
+
+    fn from_ordered_bytes(bytes: [u8; 8]) -> Self {
+        let bits = u64::from_be_bytes(bytes);
+        Self::from_bits(if bits >> 63 == 1 {
+            bits ^ (1 << 63)
+        } else {
+            !bits
+        })
+    }
+}
+
+/// Objects having given attribute with value in given range, to be searched along with tags
+#[derive(Clone, PartialEq, Eq, Debug, bincode::Encode, bincode::Decode)]
+#[bincode(crate = "bincode")]
+pub struct AttributeRange {
+    pub attribute: Id,
+    pub start: Bound<[u8; 8]>,
+    pub end: Bound<[u8; 8]>,
+}
+
+impl AttributeRange {
+    pub fn new<V: AttributeValue, R: RangeBounds<V>>(attribute: Id, range: R) -> Self {
+        let encode = |bound: Bound<&V>| match bound {
+            Bound::Included(value) => Bound::Included(value.to_ordered_bytes()),
+            Bound::Excluded(value) => Bound::Excluded(value.to_ordered_bytes()),
+            Bound::Unbounded => Bound::Unbounded,
+        };
+        Self {
+            attribute,
+            start: encode(range.start_bound()),
+            end: encode(range.end_bound()),
+        }
+    }
+
+    /// value to start iteration from, which may itself be excluded
+    pub fn first_value(&self) -> [u8; 8] {
+        match self.start {
+            Bound::Included(value) | Bound::Excluded(value) => value,
+            Bound::Unbounded => [0; 8],
+        }
+    }
+
+    pub fn is_past_end(&self, value: &[u8; 8]) -> bool {
+        match &self.end {
+            Bound::Included(end) => value > end,
+            Bound::Excluded(end) => value >= end,
+            Bound::Unbounded => false,
+        }
+    }
+
+    pub fn contains(&self, value: &[u8; 8]) -> bool {
+        (self.start.as_ref(), self.end.as_ref()).contains(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn assert_order_preserved<V: AttributeValue + PartialOrd + std::fmt::Debug>(values: &[V]) {
+        for pair in values.windows(2) {
+            assert!(pair[0] < pair[1]);
+            assert!(
+                pair[0].to_ordered_bytes() < pair[1].to_ordered_bytes(),
+                "{pair:?}"
+            );
+        }
+        for value in values {
+            assert_eq!(V::from_ordered_bytes(value.to_ordered_bytes()), *value);
+        }
+    }
+
+    #[test]
+    fn test_ordered_bytes() {
+        assert_order_preserved(&[0u64, 1, 255, 256, u64::MAX]);
+        assert_order_preserved(&[i64::MIN, -256, -1, 0, 1, 255, i64::MAX]);
+        assert_order_preserved(&[
+            f64::NEG_INFINITY,
+            -1e300,
+            -1.5,
+            -f64::MIN_POSITIVE,
+            0.0,
+            f64::MIN_POSITIVE,
+            1.5,
+            1e300,
+            f64::INFINITY,
+        ]);
+        assert!((-0.0f64).to_ordered_bytes() < 0.0f64.to_ordered_bytes());
+        assert!(f64::NAN.to_ordered_bytes() > f64::INFINITY.to_ordered_bytes());
+        assert!((-f64::NAN).to_ordered_bytes() < f64::NEG_INFINITY.to_ordered_bytes());
+        assert!(f64::from_ordered_bytes(f64::NAN.to_ordered_bytes()).is_nan());
+    }
+
+    #[test]
+    fn test_range() {
+        let attribute = Id([1; 16]);
+        let range = AttributeRange::new(attribute.clone(), 10u64..20);
+        assert_eq!(range.first_value(), 10u64.to_ordered_bytes());
+        assert!(range.contains(&10u64.to_ordered_bytes()));
+        assert!(!range.contains(&20u64.to_ordered_bytes()));
+        assert!(range.is_past_end(&20u64.to_ordered_bytes()));
+        assert!(!range.is_past_end(&19u64.to_ordered_bytes()));
+
+        let range = AttributeRange::new(attribute, (Bound::Excluded(-1i64), Bound::Unbounded));
+        assert!(!range.contains(&(-1i64).to_ordered_bytes()));
+        assert!(range.contains(&i64::MAX.to_ordered_bytes()));
+        assert!(!range.is_past_end(&i64::MAX.to_ordered_bytes()));
+    }
+}
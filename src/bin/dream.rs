@@ -0,0 +1,291 @@
+use std::{fs, io::BufReader, ops::Bound, process::ExitCode};
+
+use anyhow::{Context, Result, anyhow, bail};
+use fallible_iterator::FallibleIterator;
+
+extern crate dream;
+use dream::*;
+
+define_index!(cli_index(
+    public
+) {
+} use {
+});
+
+const USAGE: &str = "Usage: dream CONFIG COMMAND [ARGUMENTS]
+
+Opens index described by CONFIG, YAML index config with single schema named public
+
+Commands:
+    search [--present TAG]... [--absent TAG]... [--descending] [--limit N]
+    search --after TOKEN [--limit N]
+        print objects having all present tags and none of absent ones;
+        with --limit prints token of the next page to stderr, pass it as --after to continue,
+        as it holds tags and direction of the search
+    get-tags OBJECT
+        print object tags, followed by their names if known
+    insert OBJECT TAG...
+    remove OBJECT [TAG...]
+        remove given tags from object, or object itself if no tags given
+    stats [TAG...]
+        print tables sizes and cardinalities of given tags
+    verify [--repair]
+        check internal tables consistency, exit with code 1 if inconsistencies found and not repaired
+
+Objects and tags are 16-byte identifiers written as 32 hexadecimal digits";
+
+fn parse_id(text: &str) -> Result<Id> {
+    text.parse()
+        .with_context(|| format!("Can not parse identifier {text:?}"))
+}
+
+fn parse_ids(texts: &[String]) -> Result<Vec<Id>> {
+    texts.iter().map(|text| parse_id(text)).collect()
+}
+
+struct SearchArguments {
+    present_tags: Vec<Id>,
+    absent_tags: Vec<Id>,
+    direction: Direction,
+    limit: Option<usize>,
+    after: Option<SearchCursorToken>,
+}
+
+fn parse_search_arguments(arguments: &[String]) -> Result<SearchArguments> {
+    let mut result = SearchArguments {
+        present_tags: Vec::new(),
+        absent_tags: Vec::new(),
+        direction: Direction::Ascending,
+        limit: None,
+        after: None,
+    };
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        let mut value = || {
+            arguments
+                .next()
+                .ok_or_else(|| anyhow!("Option {argument} requires value"))
+        };
+        match argument.as_str() {
+            "--present" => result.present_tags.push(parse_id(value()?)?),
+            "--absent" => result.absent_tags.push(parse_id(value()?)?),
+            "--descending" => result.direction = Direction::Descending,
+            "--limit" => {
+                let value = value()?;
+                result.limit = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Can not parse limit {value:?}"))?,
+                )
+            }
+            "--after" => {
+                let value = value()?;
+                result.after = Some(
+                    value
+                        .parse()
+                        .with_context(|| format!("Can not parse search cursor token {value:?}"))?,
+                )
+            }
+            _ => bail!("Unknown search option {argument:?}"),
+        }
+    }
+    if result.after.is_some()
+        && (!result.present_tags.is_empty()
+            || !result.absent_tags.is_empty()
+            || result.direction.is_descending())
+    {
+        bail!("Option --after can not be combined with --present, --absent or --descending");
+    }
+    Ok(result)
+}
+
+fn search(index: &cli_index::Index, arguments: &[String]) -> Result<()> {
+    let arguments = parse_search_arguments(arguments)?;
+    index.lock_all_writes_and_read(|transaction| {
+        let token = arguments.after.clone().unwrap_or_else(|| {
+            transaction.public_search_token(
+                &arguments.present_tags,
+                &arguments.absent_tags,
+                arguments.direction,
+            )
+        });
+        match arguments.limit {
+            Some(limit) => {
+                let (page, next_token) = transaction.public_search_page(&token, limit)?;
+                for object in page.iter() {
+                    println!("{object}");
+                }
+                if let Some(next_token) = next_token {
+                    eprintln!("{next_token}");
+                }
+            }
+            None => transaction
+                .public_search_by_token(&token)?
+                .for_each(|object| {
+                    println!("{object}");
+                    Ok(())
+                })?,
+        }
+        Ok(())
+    })
+}
+
+fn get_tags(index: &cli_index::Index, arguments: &[String]) -> Result<()> {
+    let [object] = arguments else {
+        bail!("get-tags requires exactly one object");
+    };
+    let object = parse_id(object)?;
+    index.lock_all_writes_and_read(|transaction| {
+        for (tag, name) in transaction.public_get_tag_names(&object)? {
+            match name {
+                Some(name) => println!("{tag} {}", String::from_utf8_lossy(&name)),
+                None => println!("{tag}"),
+            }
+        }
+        Ok(())
+    })
+}
+
+fn insert(index: &mut cli_index::Index, arguments: &[String]) -> Result<()> {
+    let [object, tags @ ..] = arguments else {
+        bail!("insert requires object");
+    };
+    let object = parse_id(object)?;
+    let tags = parse_ids(tags)?;
+    if tags.is_empty() {
+        bail!("insert requires at least one tag");
+    }
+    index.lock_all_and_write(|transaction| {
+        transaction.public_insert(&object, &tags)?;
+        Ok(())
+    })
+}
+
+fn remove(index: &mut cli_index::Index, arguments: &[String]) -> Result<()> {
+    let [object, tags @ ..] = arguments else {
+        bail!("remove requires object");
+    };
+    let object = parse_id(object)?;
+    let tags = parse_ids(tags)?;
+    index.lock_all_and_write(|transaction| {
+        if tags.is_empty() {
+            transaction.public_remove_object(&object)?;
+        } else {
+            transaction.public_remove_tags_from_object(&object, &tags)?;
+        }
+        Ok(())
+    })
+}
+
+fn stats(index: &cli_index::Index, arguments: &[String]) -> Result<()> {
+    let tags = parse_ids(arguments)?;
+    index.lock_all_writes_and_read(|transaction| {
+        let mut pairs_count = 0;
+        let mut tags_count = 0;
+        let mut previous_tag = None;
+        let mut pairs = transaction
+            .database_transaction
+            .public
+            .tag_and_object
+            .iter(Bound::Unbounded, false)?;
+        while let Some(((tag, _), _)) = pairs.next()? {
+            pairs_count += 1;
+            if previous_tag.as_ref() != Some(&tag) {
+                tags_count += 1;
+                previous_tag = Some(tag);
+            }
+        }
+        println!("pairs: {pairs_count}");
+        println!("tags: {tags_count}");
+        if transaction.index_config.maintain_only_tag_and_object_table {
+            println!("objects: not maintained");
+        } else {
+            println!(
+                "objects: {}",
+                transaction
+                    .database_transaction
+                    .public
+                    .object
+                    .iter(Bound::Unbounded, false)?
+                    .count()?
+            );
+        }
+        for tag in tags.iter() {
+            println!("{tag}: {}", transaction.public_tag_cardinality(tag)?);
+        }
+        Ok(())
+    })
+}
+
+fn print_finding<T>(name: &str, finding: &Finding<T>, format: impl Fn(&T) -> String) {
+    println!("{name}: {}", finding.count);
+    for sample in finding.samples.iter() {
+        println!("    {}", format(sample));
+    }
+}
+
+fn verify(index: &mut cli_index::Index, arguments: &[String]) -> Result<ExitCode> {
+    let repair = match arguments {
+        [] => false,
+        [option] if option == "--repair" => true,
+        _ => bail!("Unknown verify arguments {arguments:?}"),
+    };
+    let report = if repair {
+        index.verify_and_repair()?
+    } else {
+        index.verify()?
+    };
+    let format_pair = |(tag, object): &(Id, Id)| format!("{tag} {object}");
+    print_finding(
+        "pairs only in tag_and_object",
+        &report.public.pairs_only_in_tag_and_object,
+        format_pair,
+    );
+    print_finding(
+        "pairs only in object_and_tag",
+        &report.public.pairs_only_in_object_and_tag,
+        format_pair,
+    );
+    print_finding(
+        "untagged objects",
+        &report.public.untagged_objects,
+        Id::to_string,
+    );
+    print_finding(
+        "missing objects",
+        &report.public.missing_objects,
+        Id::to_string,
+    );
+    Ok(if repair || report.is_consistent() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+fn main() -> Result<ExitCode> {
+    let arguments = std::env::args().skip(1).collect::<Vec<_>>();
+    let [config_path, command, arguments @ ..] = arguments.as_slice() else {
+        eprintln!("{USAGE}");
+        return Ok(ExitCode::FAILURE);
+    };
+    let config: cli_index::IndexConfig = serde_saphyr::from_reader(BufReader::new(
+        fs::File::open(config_path)
+            .with_context(|| format!("Can not open index config {config_path:?}"))?,
+    ))
+    .with_context(|| format!("Can not parse index config {config_path:?}"))?;
+    let mut index = cli_index::Index::new(config)?;
+    match command.as_str() {
+        "search" => search(&index, arguments)?,
+        "get-tags" => get_tags(&index, arguments)?,
+        "insert" => insert(&mut index, arguments)?,
+        "remove" => remove(&mut index, arguments)?,
+        "stats" => stats(&index, arguments)?,
+        "verify" => return verify(&mut index, arguments),
+        _ => {
+            eprintln!("Unknown command {command:?}\n\n{USAGE}");
+            return Ok(ExitCode::FAILURE);
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
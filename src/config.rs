@@ -0,0 +1,129 @@
+use serde::{Deserialize, Deserializer, de::DeserializeOwned, de::Error};
+use serde_json::{Map, Value};
+
+/// Tables added to schemas after `tag_and_object`, `object_and_tag` and `object` ones, with encoded entry size of fixed ones
+const OPTIONAL_TABLES: [(&str, Option<usize>); 6] = [
+    ("layout", None),
+    ("tag_count", Some(24)),
+    ("tag_dictionary", None),
+    ("tag_name", None),
+    ("attribute_and_object", Some(40)),
+    ("object_and_attribute", Some(40)),
+];
+
+const BASE_TABLE: &str = "tag_and_object";
+
+fn derived_path(base_path: &Value, table: &str) -> Result<String, String> {
+    let base_path = base_path
+        .as_str()
+        .ok_or_else(|| format!("{BASE_TABLE} table path {base_path} is not a string"))?;
+    if !base_path.contains(BASE_TABLE) {
+        return Err(format!(
+            "Can not derive {table} table config as {BASE_TABLE} table path {base_path:?} does not contain {BASE_TABLE:?}"
+        ));
+    }
+    Ok(base_path.replace(BASE_TABLE, table))
+}
+
+// configs of optional tables missing from schema config are derived from tag_and_object table config by replacing
+// its name in paths, so configs written before these tables were introduced can still be used
+pub fn complete_schema_config(schema_config: &mut Map<String, Value>) -> Result<(), String> {
+    let base = schema_config
+        .get(BASE_TABLE)
+        .ok_or_else(|| format!("Schema config has no {BASE_TABLE} table"))?
+        .clone();
+    for (table, container_size) in OPTIONAL_TABLES {
+        if schema_config.contains_key(table) {
+            continue;
+        }
+        let index_path = derived_path(&base["index"]["path"], table)?;
+        let base_data_pool = base["data_pool"]
+            .as_object()
+            .and_then(|data_pool| data_pool.values().next())
+            .ok_or_else(|| format!("{BASE_TABLE} table config has no data pool"))?;
+        let data_path = derived_path(&base_data_pool["path"], table)?;
+        let data_pool = match container_size {
+            Some(container_size) => serde_json::json!({
+                "fixed": { "path": data_path, "container_size": container_size }
+            }),
+            None => serde_json::json!({
+                "variable": { "path": data_path.strip_suffix(".dat").unwrap_or(&data_path) }
+            }),
+        };
+        schema_config.insert(
+            table.to_string(),
+            serde_json::json!({ "index": { "path": index_path }, "data_pool": data_pool }),
+        );
+    }
+    Ok(())
+}
+
+/// Used by `define_index!` for `database` field of index config, completing configs of given schemas
+pub fn deserialize_database_config<'de, D: Deserializer<'de>, C: DeserializeOwned>(
+    deserializer: D,
+    schemas: &[&str],
+) -> Result<C, D::Error> {
+    let mut database_config = Value::deserialize(deserializer)?;
+    for schema in schemas {
+        if let Some(schema_config) = database_config
+            .get_mut("schemas")
+            .and_then(|schemas| schemas.get_mut(schema))
+            .and_then(Value::as_object_mut)
+        {
+            complete_schema_config(schema_config).map_err(D::Error::custom)?;
+        }
+    }
+    serde_json::from_value(database_config).map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn table_config(name: &str) -> Value {
+        serde_json::json!({
+            "index": { "path": format!("/tables/{name}/index.idx") },
+            "data_pool": { "fixed": { "path": format!("/tables/{name}/data.dat"), "container_size": 32 } }
+        })
+    }
+
+    #[test]
+    fn test_complete_schema_config() {
+        let mut schema_config = Map::new();
+        for table in ["tag_and_object", "object_and_tag", "object"] {
+            schema_config.insert(table.to_string(), table_config(table));
+        }
+        let tag_name = serde_json::json!({ "custom": true });
+        schema_config.insert("tag_name".to_string(), tag_name.clone());
+        complete_schema_config(&mut schema_config).unwrap();
+
+        assert_eq!(schema_config.len(), 9);
+        assert_eq!(schema_config["tag_name"], tag_name);
+        assert_eq!(
+            schema_config["tag_count"],
+            serde_json::json!({
+                "index": { "path": "/tables/tag_count/index.idx" },
+                "data_pool": { "fixed": { "path": "/tables/tag_count/data.dat", "container_size": 24 } }
+            })
+        );
+        assert_eq!(
+            schema_config["layout"],
+            serde_json::json!({
+                "index": { "path": "/tables/layout/index.idx" },
+                "data_pool": { "variable": { "path": "/tables/layout/data" } }
+            })
+        );
+
+        let mut schema_config = Map::new();
+        schema_config.insert(
+            "tag_and_object".to_string(),
+            serde_json::json!({
+                "index": { "path": "/tables/pairs.idx" },
+                "data_pool": { "fixed": { "path": "/tables/pairs.dat", "container_size": 32 } }
+            }),
+        );
+        assert!(complete_schema_config(&mut schema_config).is_err());
+    }
+}
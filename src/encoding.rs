@@ -0,0 +1,167 @@
+const BASE64URL_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// unpadded base64url (RFC 4648 section 5)
+pub fn encode_base64url(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let value = chunk.iter().enumerate().fold(0u32, |value, (i, byte)| {
+            value | (*byte as u32) << (16 - 8 * i)
+        });
+        for i in 0..=chunk.len() {
+            result.push(BASE64URL_ALPHABET[(value >> (18 - 6 * i)) as usize & 0x3f] as char);
+        }
+    }
+    result
+}
+
+/// returns position of the first invalid character on error, which is the last one of text
+/// when its unused trailing bits are not zero, so that each byte string has exactly one encoding
+pub fn decode_base64url(text: &str) -> Result<Vec<u8>, usize> {
+    if text.len() % 4 == 1 {
+        return Err(text.len() - 1);
+    }
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+    for (chunk_index, chunk) in text.as_bytes().chunks(4).enumerate() {
+        let mut value = 0u32;
+        for (i, character) in chunk.iter().enumerate() {
+            let digit = BASE64URL_ALPHABET
+                .iter()
+                .position(|alphabet_character| alphabet_character == character)
+                .ok_or(chunk_index * 4 + i)?;
+            value |= (digit as u32) << (18 - 6 * i);
+        }
+        if value & ((1 << (32 - 8 * chunk.len())) - 1) != 0 {
+            return Err(chunk_index * 4 + chunk.len() - 1);
+        }
+        for i in 0..chunk.len() - 1 {
+            result.push((value >> (16 - 8 * i)) as u8);
+        }
+    }
+    Ok(result)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// unpadded lowercase base32 (RFC 4648 section 6)
+pub fn encode_base32(bytes: &[u8]) -> String {
+    let mut result = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    for chunk in bytes.chunks(5) {
+        let value = chunk.iter().enumerate().fold(0u64, |value, (i, byte)| {
+            value | (*byte as u64) << (32 - 8 * i)
+        });
+        for i in 0..(chunk.len() * 8).div_ceil(5) {
+            result.push(BASE32_ALPHABET[(value >> (35 - 5 * i)) as usize & 0x1f] as char);
+        }
+    }
+    result
+}
+
+/// accepts both cases, returns position of the first invalid character on error, which is the last one of text
+/// when its unused trailing bits are not zero
+pub fn decode_base32(text: &str) -> Result<Vec<u8>, usize> {
+    if matches!(text.len() % 8, 1 | 3 | 6) {
+        return Err(text.len() - 1);
+    }
+    let mut result = Vec::with_capacity(text.len() * 5 / 8);
+    for (chunk_index, chunk) in text.as_bytes().chunks(8).enumerate() {
+        let mut value = 0u64;
+        for (i, character) in chunk.iter().enumerate() {
+            let digit = BASE32_ALPHABET
+                .iter()
+                .position(|alphabet_character| {
+                    *alphabet_character == character.to_ascii_lowercase()
+                })
+                .ok_or(chunk_index * 8 + i)?;
+            value |= (digit as u64) << (35 - 5 * i);
+        }
+        if value & ((1 << (40 - chunk.len() * 5 / 8 * 8)) - 1) != 0 {
+            return Err(chunk_index * 8 + chunk.len() - 1);
+        }
+        for i in 0..chunk.len() * 5 / 8 {
+            result.push((value >> (32 - 8 * i)) as u8);
+        }
+    }
+    Ok(result)
+}
+
+/// lowercase hexadecimal
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+/// accepts both cases, returns position of the first invalid character on error
+pub fn decode_hex(text: &str) -> Result<Vec<u8>, usize> {
+    if text.len() % 2 == 1 {
+        return Err(text.len() - 1);
+    }
+    let digit = |position: usize| {
+        (text.as_bytes()[position] as char)
+            .to_digit(16)
+            .map(|digit| digit as u8)
+            .ok_or(position)
+    };
+    (0..text.len())
+        .step_by(2)
+        .map(|position| Ok(digit(position)? << 4 | digit(position + 1)?))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_base64url() {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "Zg"),
+            (b"fo", "Zm8"),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg"),
+            (b"fooba", "Zm9vYmE"),
+            (b"foobar", "Zm9vYmFy"),
+            (&[0xfb, 0xff, 0xbf], "-_-_"),
+        ] {
+            assert_eq!(encode_base64url(bytes), text);
+            assert_eq!(decode_base64url(text).unwrap(), bytes);
+        }
+        assert_eq!(decode_base64url("Zm9v*g"), Err(4));
+        assert_eq!(decode_base64url("Zm9vY"), Err(4));
+        assert_eq!(decode_base64url("Zh"), Err(1));
+        assert_eq!(decode_base64url("Zm9"), Err(2));
+        assert_eq!(decode_base64url("Zm9vYmF"), Err(6));
+    }
+
+    #[test]
+    fn test_base32() {
+        for (bytes, text) in [
+            (&b""[..], ""),
+            (b"f", "my"),
+            (b"fo", "mzxq"),
+            (b"foo", "mzxw6"),
+            (b"foob", "mzxw6yq"),
+            (b"fooba", "mzxw6ytb"),
+            (b"foobar", "mzxw6ytboi"),
+        ] {
+            assert_eq!(encode_base32(bytes), text);
+            assert_eq!(decode_base32(text).unwrap(), bytes);
+        }
+        assert_eq!(decode_base32("MZXW6YTBOI").unwrap(), b"foobar");
+        assert_eq!(decode_base32("mzxw1"), Err(4));
+        assert_eq!(decode_base32("mzx"), Err(2));
+        assert_eq!(decode_base32("mz"), Err(1));
+        assert_eq!(decode_base32("mzxr"), Err(3));
+        assert_eq!(decode_base32("mzxw6ytbol"), Err(9));
+    }
+
+    #[test]
+    fn test_hex() {
+        assert_eq!(encode_hex(&[0x00, 0x0f, 0xab, 0xff]), "000fabff");
+        assert_eq!(decode_hex("000FabfF").unwrap(), [0x00, 0x0f, 0xab, 0xff]);
+        assert_eq!(decode_hex("00g0"), Err(2));
+        assert_eq!(decode_hex("000"), Err(2));
+    }
+}
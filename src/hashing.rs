@@ -0,0 +1,95 @@
+use crate::Id;
+
+/// Hash function turning content into identifier; implementations must never change their output
+pub trait IdHasher {
+    fn hash(bytes: &[u8]) -> [u8; 16];
+}
+
+impl Id {
+    pub fn from_bytes_hash<H: IdHasher>(bytes: &[u8]) -> Self {
+        Self(H::hash(bytes))
+    }
+
+    pub fn from_str_hash<H: IdHasher>(text: &str) -> Self {
+        Self::from_bytes_hash::<H>(text.as_bytes())
+    }
+}
+
+/// XXH3 128-bit, big-endian as in canonical xxHash representation
+#[cfg(feature = "xxh3")]
+pub struct Xxh3;
+
+#[cfg(feature = "xxh3")]
+impl IdHasher for Xxh3 {
+    fn hash(bytes: &[u8]) -> [u8; 16] {
+        xxhash_rust::xxh3::xxh3_128(bytes).to_be_bytes()
+    }
+}
+
+/// BLAKE3 truncated to the first 16 bytes
+#[cfg(feature = "blake3")]
+pub struct Blake3;
+
+#[cfg(feature = "blake3")]
+impl IdHasher for Blake3 {
+    fn hash(bytes: &[u8]) -> [u8; 16] {
+        blake3::hash(bytes).as_bytes()[..16].try_into().unwrap()
+    }
+}
+
+/// SipHash-2-4 128-bit with zero key, little-endian
+#[cfg(feature = "siphash")]
+pub struct SipHash128;
+
+#[cfg(feature = "siphash")]
+impl IdHasher for SipHash128 {
+    fn hash(bytes: &[u8]) -> [u8; 16] {
+        siphasher::sip128::SipHasher24::new().hash(bytes).as_bytes()
+    }
+}
+
+#[cfg(all(test, any(feature = "xxh3", feature = "blake3", feature = "siphash")))]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[cfg(feature = "xxh3")]
+    #[test]
+    fn test_xxh3() {
+        assert_eq!(
+            Id::from_str_hash::<Xxh3>("").to_string(),
+            "99aa06d3014798d86001c324468d497f"
+        );
+        assert_eq!(
+            Id::from_str_hash::<Xxh3>("dream").to_string(),
+            "fd8a49894c3ea972a8edf59e01872ca6"
+        );
+    }
+
+    #[cfg(feature = "blake3")]
+    #[test]
+    fn test_blake3() {
+        assert_eq!(
+            Id::from_str_hash::<Blake3>("").to_string(),
+            "af1349b9f5f9a1a6a0404dea36dcc949"
+        );
+        assert_eq!(
+            Id::from_str_hash::<Blake3>("dream").to_string(),
+            "fa9fade9f39cb1f7ab4f6fb83782a114"
+        );
+    }
+
+    #[cfg(feature = "siphash")]
+    #[test]
+    fn test_siphash() {
+        assert_eq!(
+            Id::from_str_hash::<SipHash128>("").to_string(),
+            "5049d74780a3e07d4202ab47d4cef2f4"
+        );
+        assert_eq!(
+            Id::from_bytes_hash::<SipHash128>(b"dream").to_string(),
+            "6e348e548b66fe513a4ad6cf67a02031"
+        );
+    }
+}
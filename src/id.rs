@@ -0,0 +1,244 @@
+use crate::{bincode, encoding};
+
+/// 16-byte identifier of object or tag, written as lowercase hex by default
+#[derive(
+    Clone, Default, PartialEq, PartialOrd, bincode::Encode, bincode::Decode, Eq, Ord, Hash,
+)]
+#[bincode(crate = "bincode")]
+pub struct Id(pub [u8; 16]);
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum IdFormat {
+    /// 32 characters
+    #[default]
+    Hex,
+    /// unpadded, 22 characters
+    Base64Url,
+    /// unpadded lowercase RFC 4648 alphabet, 26 characters
+    Base32,
+}
+
+impl IdFormat {
+    pub fn text_length(self) -> usize {
+        match self {
+            Self::Hex => 32,
+            Self::Base64Url => 22,
+            Self::Base32 => 26,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdParseError {
+    WrongLength {
+        format: IdFormat,
+        expected: usize,
+        found: usize,
+    },
+    InvalidCharacter {
+        format: IdFormat,
+        position: usize,
+    },
+}
+
+impl std::fmt::Display for IdParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongLength {
+                format,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Identifier in {format:?} format must be {expected} characters long, found {found}"
+            ),
+            Self::InvalidCharacter { format, position } => write!(
+                f,
+                "Identifier has character invalid for {format:?} format at position {position}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IdParseError {}
+
+impl Id {
+    pub fn to_string_in(&self, format: IdFormat) -> String {
+        match format {
+            IdFormat::Hex => encoding::encode_hex(&self.0),
+            IdFormat::Base64Url => encoding::encode_base64url(&self.0),
+            IdFormat::Base32 => encoding::encode_base32(&self.0),
+        }
+    }
+
+    pub fn parse_in(text: &str, format: IdFormat) -> Result<Self, IdParseError> {
+        if text.len() != format.text_length() {
+            return Err(IdParseError::WrongLength {
+                format,
+                expected: format.text_length(),
+                found: text.len(),
+            });
+        }
+        let bytes = match format {
+            IdFormat::Hex => encoding::decode_hex(text),
+            IdFormat::Base64Url => encoding::decode_base64url(text),
+            IdFormat::Base32 => encoding::decode_base32(text),
+        }
+        .map_err(|position| IdParseError::InvalidCharacter { format, position })?;
+        Ok(Self(bytes.try_into().unwrap()))
+    }
+}
+
+impl std::fmt::Display for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_string_in(IdFormat::Hex))
+    }
+}
+
+impl std::fmt::Debug for Id {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Id({self})")
+    }
+}
+
+impl std::str::FromStr for Id {
+    type Err = IdParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Self::parse_in(text, IdFormat::Hex)
+    }
+}
+
+// hex string for human-readable formats, 16 bytes otherwise
+impl serde::Serialize for Id {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_string())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+struct IdVisitor;
+
+impl<'de> serde::de::Visitor<'de> for IdVisitor {
+    type Value = Id;
+
+    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("32 hexadecimal digits or 16 bytes")
+    }
+
+    fn visit_str<E: serde::de::Error>(self, text: &str) -> Result<Self::Value, E> {
+        text.parse().map_err(E::custom)
+    }
+
+    fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+        Ok(Id(bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &self))?))
+    }
+
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut result = [0u8; 16];
+        for (index, byte) in result.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| serde::de::Error::invalid_length(index, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(serde::de::Error::invalid_length(17, &self));
+        }
+        Ok(Id(result))
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Id {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(IdVisitor)
+        } else {
+            deserializer.deserialize_bytes(IdVisitor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_text_formats() {
+        let id = Id([
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0xaa, 0xbb, 0xcc, 0xdd,
+            0xee, 0xff,
+        ]);
+        for (format, text) in [
+            (IdFormat::Hex, "00112233445566778899aabbccddeeff"),
+            (IdFormat::Base64Url, "ABEiM0RVZneImaq7zN3u_w"),
+            (IdFormat::Base32, "aaisem2ekvthpcezvk54zxpo74"),
+        ] {
+            assert_eq!(id.to_string_in(format), text);
+            assert_eq!(Id::parse_in(text, format).unwrap(), id);
+        }
+        assert_eq!(id.to_string(), "00112233445566778899aabbccddeeff");
+        assert_eq!(format!("{id:?}"), "Id(00112233445566778899aabbccddeeff)");
+        assert_eq!(
+            "00112233445566778899AABBCCDDEEFF".parse::<Id>().unwrap(),
+            id
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "0011".parse::<Id>(),
+            Err(IdParseError::WrongLength {
+                format: IdFormat::Hex,
+                expected: 32,
+                found: 4
+            })
+        );
+        assert_eq!(
+            "00112233445566778899aabbccddeefg".parse::<Id>(),
+            Err(IdParseError::InvalidCharacter {
+                format: IdFormat::Hex,
+                position: 31
+            })
+        );
+        assert_eq!(
+            Id::parse_in("ABEiM0RVZneImaq7zN3u*w", IdFormat::Base64Url),
+            Err(IdParseError::InvalidCharacter {
+                format: IdFormat::Base64Url,
+                position: 20
+            })
+        );
+        for (format, text) in [
+            (IdFormat::Base64Url, "ABEiM0RVZneImaq7zN3u_x"),
+            (IdFormat::Base32, "aaisem2ekvthpcezvk54zxpo75"),
+        ] {
+            assert_eq!(
+                Id::parse_in(text, format),
+                Err(IdParseError::InvalidCharacter {
+                    format,
+                    position: text.len() - 1
+                })
+            );
+        }
+    }
+
+    #[test]
+    fn test_serde() {
+        let id = Id([0xab; 16]);
+        let json = serde_json::to_string(&id).unwrap();
+        assert_eq!(json, "\"abababababababababababababababab\"");
+        assert_eq!(serde_json::from_str::<Id>(&json).unwrap(), id);
+        assert_eq!(
+            serde_saphyr::from_str::<Vec<Id>>("- abababababababababababababababab").unwrap(),
+            std::slice::from_ref(&id)
+        );
+        assert!(serde_json::from_str::<Id>("\"abab\"").is_err());
+    }
+}
@@ -6,11 +6,306 @@ pub extern crate serde;
 
 pub use lawn::bincode;
 
-#[derive(
-    Clone, Default, PartialEq, PartialOrd, Debug, bincode::Encode, bincode::Decode, Eq, Ord, Hash,
-)]
+use fallible_iterator::FallibleIterator;
+
+pub mod attribute;
+pub mod config;
+pub mod encoding;
+pub mod hashing;
+pub mod id;
+pub mod query_parser;
+pub mod search_cursor_token;
+pub mod set_operations;
+#[cfg(feature = "sonic")]
+pub mod sonic;
+#[cfg(feature = "text")]
+pub mod text;
+
+pub use attribute::{AttributeRange, AttributeValue};
+pub use hashing::IdHasher;
+pub use id::{Id, IdFormat, IdParseError};
+pub use search_cursor_token::{SearchCursorToken, SearchCursorTokenError};
+#[cfg(feature = "text")]
+pub use text::TextTagger;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bincode::Encode, bincode::Decode)]
+#[bincode(crate = "bincode")]
+pub enum Direction {
+    #[default]
+    Ascending,
+    /// `start_after_object` becomes `start_before_object` keyset: search resumes from the greatest object less than it
+    Descending,
+}
+
+impl Direction {
+    pub fn is_descending(self) -> bool {
+        self == Direction::Descending
+    }
+
+    pub fn precedes<T: Ord>(self, left: &T, right: &T) -> bool {
+        match self {
+            Direction::Ascending => left < right,
+            Direction::Descending => left > right,
+        }
+    }
+
+    pub fn first_id(self) -> Id {
+        match self {
+            Direction::Ascending => Id::default(),
+            Direction::Descending => Id([u8::MAX; 16]),
+        }
+    }
+}
+
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct TagsDiff {
+    pub added: Vec<Id>,
+    pub removed: Vec<Id>,
+}
+
+/// Operation needs `object_and_tag` or `object` table, which is not maintained when `maintain_only_tag_and_object_table` is set
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct LeanModeError {
+    pub operation: &'static str,
+}
+
+impl std::fmt::Display for LeanModeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is not supported for index maintaining only tag_and_object table",
+            self.operation
+        )
+    }
+}
+
+impl std::error::Error for LeanModeError {}
+
+/// Version 0 is the layout without `layout` table, having only `tag_and_object`, `object_and_tag` and `object` tables
+pub const LAYOUT_VERSION: u64 = 1;
+
+/// Stored per schema in `layout` table and brought in line with index config on `Index::new`
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bincode::Encode, bincode::Decode)]
+#[bincode(crate = "bincode")]
+pub struct SchemaLayout {
+    pub version: u64,
+    pub maintains_tag_count_table: bool,
+    pub object_tables: ObjectTables,
+}
+
+/// State of `object_and_tag` and `object` tables, which are read from only while complete in every schema
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug, bincode::Encode, bincode::Decode)]
 #[bincode(crate = "bincode")]
-pub struct Id(pub [u8; 16]);
+pub enum ObjectTables {
+    #[default]
+    Complete,
+    /// written to while being rebuilt from `tag_and_object` table, yet not read from
+    Building,
+    /// neither written to nor read from, possibly holding rows left to drop
+    Absent,
+}
+
+const FINDING_SAMPLES_LIMIT: usize = 16;
+const REPAIR_CHUNK_SIZE: usize = 1024;
+
+/// Inconsistencies of one kind: their total count and up to 16 first of them
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Finding<T> {
+    pub count: usize,
+    pub samples: Vec<T>,
+}
+
+impl<T> Finding<T> {
+    // counts items while streaming them, so only samples are kept in memory
+    pub fn from_items<I: FallibleIterator<Item = T>>(mut items: I) -> Result<Self, I::Error> {
+        let mut result = Self {
+            count: 0,
+            samples: Vec::new(),
+        };
+        while let Some(item) = items.next()? {
+            result.push(item);
+        }
+        Ok(result)
+    }
+
+    pub fn push(&mut self, item: T) {
+        self.count += 1;
+        if self.samples.len() < FINDING_SAMPLES_LIMIT {
+            self.samples.push(item);
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+}
+
+impl<T: Clone> Finding<T> {
+    /// Items are found and repaired in chunks, each found after the last item of the previous one
+    pub fn from_repaired_chunks<S>(
+        state: &mut S,
+        find: impl for<'a> Fn(
+            &'a S,
+            Option<T>,
+        ) -> anyhow::Result<
+            Box<dyn FallibleIterator<Item = T, Error = anyhow::Error> + 'a>,
+        >,
+        mut repair: impl FnMut(&mut S, &T),
+    ) -> anyhow::Result<Self> {
+        let mut result = Self {
+            count: 0,
+            samples: Vec::new(),
+        };
+        let mut start_after = None;
+        loop {
+            let chunk = find(state, start_after)?
+                .take(REPAIR_CHUNK_SIZE)
+                .collect::<Vec<_>>()?;
+            for item in chunk.iter() {
+                repair(state, item);
+                result.push(item.clone());
+            }
+            if chunk.len() < REPAIR_CHUNK_SIZE {
+                return Ok(result);
+            }
+            start_after = chunk.last().cloned();
+        }
+    }
+}
+
+/// Findings about `object` table are relative to `object_and_tag` table, pairs are given as (tag, object)
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct SchemaVerificationReport {
+    pub pairs_only_in_tag_and_object: Finding<(Id, Id)>,
+    pub pairs_only_in_object_and_tag: Finding<(Id, Id)>,
+    pub untagged_objects: Finding<Id>,
+    pub missing_objects: Finding<Id>,
+}
+
+impl SchemaVerificationReport {
+    pub fn is_consistent(&self) -> bool {
+        self.pairs_only_in_tag_and_object.is_empty()
+            && self.pairs_only_in_object_and_tag.is_empty()
+            && self.untagged_objects.is_empty()
+            && self.missing_objects.is_empty()
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Query {
+    Tag(Id),
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+/// Query as conjunction: objects having present tags, found by `included` queries and found by neither absent tags nor `excluded` queries
+#[derive(Default, PartialEq, Debug)]
+pub struct QueryConjuncts<'a> {
+    pub present_tags: Vec<Id>,
+    pub absent_tags: Vec<Id>,
+    pub included: Vec<&'a Query>,
+    pub excluded: Vec<&'a Query>,
+}
+
+impl Query {
+    // nested conjunctions are flattened, double negations dropped and negated disjunctions turned into conjunctions of negations
+    fn add_conjuncts<'a>(&'a self, negated: bool, conjuncts: &mut QueryConjuncts<'a>) {
+        match (self, negated) {
+            (Query::Tag(tag), false) => conjuncts.present_tags.push(tag.clone()),
+            (Query::Tag(tag), true) => conjuncts.absent_tags.push(tag.clone()),
+            (Query::And(subqueries), false) | (Query::Or(subqueries), true) => {
+                for subquery in subqueries {
+                    subquery.add_conjuncts(negated, conjuncts);
+                }
+            }
+            (Query::Not(inner), negated) => inner.add_conjuncts(!negated, conjuncts),
+            (query, false) => conjuncts.included.push(query),
+            (query, true) => conjuncts.excluded.push(query),
+        }
+    }
+
+    pub fn conjuncts(&self) -> QueryConjuncts<'_> {
+        let mut result = QueryConjuncts::default();
+        self.add_conjuncts(false, &mut result);
+        result
+    }
+
+    pub fn to_present_and_absent_tags(&self) -> Option<(Vec<Id>, Vec<Id>)> {
+        let mut present_tags = Vec::new();
+        let mut absent_tags = Vec::new();
+        let subqueries = match self {
+            Query::And(subqueries) => subqueries.as_slice(),
+            query => std::slice::from_ref(query),
+        };
+        for subquery in subqueries {
+            match subquery {
+                Query::Tag(tag) => present_tags.push(tag.clone()),
+                Query::Not(inner) => match inner.as_ref() {
+                    Query::Tag(tag) => absent_tags.push(tag.clone()),
+                    _ => return None,
+                },
+                _ => return None,
+            }
+        }
+        Some((present_tags, absent_tags))
+    }
+}
+
+impl std::ops::BitAnd for Query {
+    type Output = Query;
+
+    fn bitand(self, other: Query) -> Query {
+        match (self, other) {
+            (Query::And(mut left), Query::And(right)) => {
+                left.extend(right);
+                Query::And(left)
+            }
+            (Query::And(mut left), right) => {
+                left.push(right);
+                Query::And(left)
+            }
+            (left, Query::And(mut right)) => {
+                right.insert(0, left);
+                Query::And(right)
+            }
+            (left, right) => Query::And(vec![left, right]),
+        }
+    }
+}
+
+impl std::ops::BitOr for Query {
+    type Output = Query;
+
+    fn bitor(self, other: Query) -> Query {
+        match (self, other) {
+            (Query::Or(mut left), Query::Or(right)) => {
+                left.extend(right);
+                Query::Or(left)
+            }
+            (Query::Or(mut left), right) => {
+                left.push(right);
+                Query::Or(left)
+            }
+            (left, Query::Or(mut right)) => {
+                right.insert(0, left);
+                Query::Or(right)
+            }
+            (left, right) => Query::Or(vec![left, right]),
+        }
+    }
+}
+
+impl std::ops::Not for Query {
+    type Output = Query;
+
+    fn not(self) -> Query {
+        match self {
+            Query::Not(inner) => *inner,
+            other => Query::Not(Box::new(other)),
+        }
+    }
+}
 
 #[macro_export]
 macro_rules! define_index {
@@ -38,6 +333,12 @@ macro_rules! define_index {
                     tag_and_object<(Id, Id), ()>
                     object_and_tag<(Id, Id), ()>
                     object<Id, ()>
+                    layout<(), SchemaLayout>
+                    tag_count<Id, u64>
+                    tag_dictionary<(Vec<u8>, Id), ()>
+                    tag_name<Id, Vec<u8>>
+                    attribute_and_object<(Id, [u8; 8], Id), ()>
+                    object_and_attribute<(Id, Id), [u8; 8]>
                 }
             )*
             $(
@@ -48,7 +349,7 @@ macro_rules! define_index {
                 }
             )*
         } use {
-            use $crate::Id;
+            use $crate::{Id, SchemaLayout};
             $($use_item)*
         });
 
@@ -59,13 +360,48 @@ macro_rules! define_index {
             anyhow::{Context, Result, Error},
             fallible_iterator::FallibleIterator,
             serde::{Deserialize, Serialize},
+            Direction,
             Id
         };
 
         #[derive(Serialize, Deserialize, Debug, Clone)]
         pub struct IndexConfig {
+            #[serde(deserialize_with = "deserialize_database_config")]
             pub database: lawn_database::DatabaseConfig,
-            pub maintain_only_tag_and_object_table: bool
+            pub maintain_only_tag_and_object_table: bool,
+            #[serde(default)]
+            pub maintain_tag_count_table: bool,
+            #[serde(default)]
+            pub maintain_tag_dictionary_table: bool,
+            #[serde(default)]
+            pub maintain_tag_name_table: bool,
+            #[serde(default)]
+            pub maintain_attribute_tables: bool
+        }
+
+        fn deserialize_database_config<'de, D: $crate::serde::Deserializer<'de>>(
+            deserializer: D,
+        ) -> std::result::Result<lawn_database::DatabaseConfig, D::Error> {
+            $crate::config::deserialize_database_config(deserializer, &[$(stringify!($schema_name)),*])
+        }
+
+        const CARDINALITY_ESTIMATION_LIMIT: usize = 1024;
+        const CARDINALITY_ESTIMATION_BUDGET: usize = 4 * CARDINALITY_ESTIMATION_LIMIT;
+        const CURSOR_STEPS_BEFORE_SEEK: usize = 8;
+        const TAG_OBJECTS_CHUNK_SIZE: usize = 1024;
+        const ATTRIBUTE_VALUES_MERGE_LIMIT: usize = 64;
+
+        #[derive(Clone, Default, PartialEq, Eq, Debug)]
+        pub struct VerificationReport {
+            $(
+                pub $schema_name: $crate::SchemaVerificationReport,
+            )*
+        }
+
+        impl VerificationReport {
+            pub fn is_consistent(&self) -> bool {
+                true $(&& self.$schema_name.is_consistent())*
+            }
         }
 
         pub struct Index {
@@ -75,6 +411,7 @@ macro_rules! define_index {
 
         pub struct ReadTransaction<'a> {
             pub database_transaction: lawn_database::ReadTransaction<'a>,
+            pub index_config: &'a IndexConfig
         }
 
         pub struct WriteTransaction<'a, 'b> {
@@ -87,6 +424,13 @@ macro_rules! define_index {
                 $(
                     paste! {
                         pub fn [<$schema_name _has_tag>](&self, object: &Id, tag: &Id) -> Result<bool> {
+                            if self.index_config.maintain_only_tag_and_object_table {
+                                let key = &(tag.clone(), object.clone());
+                                return self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"));
+                            }
                             let key = &(object.clone(), tag.clone());
                             self.database_transaction
                                 .$schema_name
@@ -106,7 +450,12 @@ macro_rules! define_index {
                                 .is_some())
                         }
 
+                        // fails with LeanModeError when index maintains only tag_and_object table, as would a scan of it all;
+                        // so do set_tags, remove_object and get_tag_names relying on it
                         pub fn [<$schema_name _get_tags>](&self, object: &Id) -> Result<Vec<Id>> {
+                            if self.index_config.maintain_only_tag_and_object_table {
+                                return Err($crate::LeanModeError { operation: "Getting object tags" }.into());
+                            }
                             let from_object_and_tag = &(object.clone(), Id::default());
                             self.database_transaction
                                 .$schema_name
@@ -117,554 +466,4366 @@ macro_rules! define_index {
                                 .collect::<Vec<_>>()
                         }
 
-                        pub fn [<$schema_name _search>](
-                            &self,
-                            present_tags: &[Id],
-                            absent_tags: &[Id],
-                            start_after_object: Option<Id>,
-                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
-                            Ok(match present_tags.len() {
-                                0 => {
-                                    let from_object = start_after_object.clone().unwrap_or_default();
-                                    let absent_tags = absent_tags.to_vec();
-                                    Box::new(
-                                        self.database_transaction
-                                            .$schema_name
-                                            .object
-                                            .iter(
-                                                if let Some(start_after_object) = &start_after_object {
-                                                    Bound::Excluded(start_after_object)
-                                                } else {
-                                                    Bound::Unbounded
-                                                },
-                                                false
-                                            ).with_context(|| format!("Can not initiate iteration over object_to_tags_count table starting from key {from_object:?}"))?
-                                            .map(|(object_id, _)| Ok(object_id))
-                                            .filter(move |object_id| {
-                                                for absent_tag in absent_tags.iter() {
-                                                    let key = &(absent_tag.clone(), object_id.clone());
-                                                    if self
-                                                        .database_transaction
-                                                        .$schema_name
-                                                        .tag_and_object
-                                                        .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
-                                                    {
-                                                        return Ok(false)
-                                                    }
-                                                }
-                                                Ok(true)
-                                            })
-                                    )
-                                },
-                                1 => {
-                                    let search_tag_id = present_tags.into_iter().next().unwrap().clone();
-                                    let from_tag_and_object = (search_tag_id.clone(), start_after_object.clone().unwrap_or_default());
-                                    let absent_tags = absent_tags.to_vec();
-                                    Box::new(
-                                        self.database_transaction
-                                            .$schema_name
-                                            .tag_and_object
-                                            .iter(Bound::Included(&from_tag_and_object), false).with_context(|| format!("Can not initiate iteration over tag_and_object table starting from key {from_tag_and_object:?}"))?
-                                            .map(|((tag_id, object_id), _)| Ok((tag_id, object_id)))
-                                            .take_while(move |(tag_id, _)| Ok(tag_id == &search_tag_id))
-                                            .map(|(_, object_id)| Ok(object_id))
-                                            .filter(move |object_id| Ok(start_after_object.is_none() || *object_id != from_tag_and_object.1))
-                                            .filter(move |object_id| {
-                                                for absent_tag in absent_tags.iter() {
-                                                    let key = &(absent_tag.clone(), object_id.clone());
-                                                    if self
-                                                        .database_transaction
-                                                        .$schema_name
-                                                        .tag_and_object
-                                                        .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
-                                                    {
-                                                        return Ok(false)
-                                                    }
-                                                }
-                                                Ok(true)
-                                            }),
-                                    )
-                                }
-                                2.. => Box::new([<$schema_name:camel SearchIterator>] {
-                                    database_transaction: self.database_transaction.deref(),
-                                    absent_tags_ids: absent_tags.to_vec(),
-                                    present_tags_ids: present_tags.to_vec(),
-                                    start_after_object,
-                                    cursors: Vec::new(),
-                                    index_1: 0 as usize,
-                                    index_2: 1 as usize,
-                                    end: false,
-                                }),
-                            })
+                        pub fn [<$schema_name _get_tag_name>](&self, tag: &Id) -> Result<Option<Vec<u8>>> {
+                            Ok(self
+                                .database_transaction
+                                .$schema_name
+                                .tag_name
+                                .iter(Bound::Included(tag), false).with_context(|| format!("Can not initiate iteration over tag_name table starting from key {tag:?}"))?
+                                .next()?
+                                .filter(|(current_tag, _)| current_tag == tag)
+                                .map(|(_, name)| name))
                         }
-                    }
-                )+
-            };
-        }
 
-        impl<'a> ReadTransaction<'a> {
-            define_read_methods!();
-        }
+                        // tags inserted without names are paired with None
+                        pub fn [<$schema_name _get_tag_names>](&self, object: &Id) -> Result<Vec<(Id, Option<Vec<u8>>)>> {
+                            self.[<$schema_name _get_tags>](object)?
+                                .into_iter()
+                                .map(|tag| {
+                                    let name = self.[<$schema_name _get_tag_name>](&tag)?;
+                                    Ok((tag, name))
+                                })
+                                .collect()
+                        }
 
-        impl<'a, 'b> WriteTransaction<'a, 'b> {
-            define_read_methods!();
+                        fn [<$schema_name _get_encoded_attribute>](&self, object: &Id, attribute: &Id) -> Result<Option<[u8; 8]>> {
+                            let key = (object.clone(), attribute.clone());
+                            Ok(self
+                                .database_transaction
+                                .$schema_name
+                                .object_and_attribute
+                                .iter(Bound::Included(&key), false).with_context(|| format!("Can not initiate iteration over object_and_attribute table starting from key {key:?}"))?
+                                .next()?
+                                .filter(|(current_key, _)| current_key == &key)
+                                .map(|(_, value)| value))
+                        }
 
-            $(
-                paste! {
-                    pub fn [<$schema_name _insert>](&mut self, object: &Id, tags: &[Id]) -> Result<&mut Self> {
-                        for tag in tags {
+                        pub fn [<$schema_name _get_attribute>]<V: $crate::AttributeValue>(&self, object: &Id, attribute: &Id) -> Result<Option<V>> {
+                            Ok(self.[<$schema_name _get_encoded_attribute>](object, attribute)?.map(V::from_ordered_bytes))
+                        }
+
+                        // values are returned encoded, as attributes of one object may be of different types
+                        pub fn [<$schema_name _get_attributes>](&self, object: &Id) -> Result<Vec<(Id, [u8; 8])>> {
+                            let from_object_and_attribute = (object.clone(), Id::default());
                             self.database_transaction
                                 .$schema_name
-                                .tag_and_object
-                                .insert((tag.clone(), object.clone()), ());
-                            if !self.index_config.maintain_only_tag_and_object_table {
+                                .object_and_attribute
+                                .iter(Bound::Included(&from_object_and_attribute), false).with_context(|| format!("Can not initiate iteration over object_and_attribute table starting from key {from_object_and_attribute:?}"))?
+                                .take_while(|((current_object, _), _)| Ok(current_object == object))
+                                .map(|((_, attribute), value)| Ok((attribute, value)))
+                                .collect::<Vec<_>>()
+                        }
+
+                        // objects having attribute with exactly given value, read like objects tagged with a tag
+                        fn [<$schema_name _attribute_value_objects>](
+                            &self,
+                            attribute: &Id,
+                            value: [u8; 8],
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let search_attribute = attribute.clone();
+                            let from_attribute_and_object = (attribute.clone(), value, start_after_object.clone().unwrap_or(direction.first_id()));
+                            Ok(Box::new(
                                 self.database_transaction
                                     .$schema_name
-                                    .object_and_tag
-                                    .insert((object.clone(), tag.clone()), ());
+                                    .attribute_and_object
+                                    .iter(Bound::Included(&from_attribute_and_object), direction.is_descending()).with_context(|| format!("Can not initiate iteration over attribute_and_object table starting from key {from_attribute_and_object:?}"))?
+                                    .map(|(key, _)| Ok(key))
+                                    .take_while(move |(current_attribute, current_value, _)| Ok(current_attribute == &search_attribute && *current_value == value))
+                                    .map(|(_, _, object)| Ok(object))
+                                    .filter(move |object| Ok(start_after_object.is_none() || *object != from_attribute_and_object.2)),
+                            ))
+                        }
+
+                        // entries are ordered by value, so each value in range is found by seeking past the previous one; objects having up to
+                        // ATTRIBUTE_VALUES_MERGE_LIMIT distinct values are merged like objects of different tags, while objects having more
+                        // are read from the range at once and sorted, as a cursor per value would cost more than that
+                        fn [<$schema_name _attribute_range_objects>](
+                            &self,
+                            range: &$crate::AttributeRange,
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let mut values = Vec::new();
+                            let mut from_attribute_and_object = Bound::Included((range.attribute.clone(), range.first_value(), Id::default()));
+                            loop {
+                                let next_key = self
+                                    .database_transaction
+                                    .$schema_name
+                                    .attribute_and_object
+                                    .iter(from_attribute_and_object.as_ref(), false).with_context(|| format!("Can not initiate iteration over attribute_and_object table starting from key {from_attribute_and_object:?}"))?
+                                    .next()?;
+                                let Some(((attribute, value, _), _)) = next_key else {
+                                    break;
+                                };
+                                if attribute != range.attribute || range.is_past_end(&value) {
+                                    break;
+                                }
+                                if range.contains(&value) {
+                                    if values.len() == ATTRIBUTE_VALUES_MERGE_LIMIT {
+                                        return self.[<$schema_name _sorted_attribute_range_objects>](range, start_after_object, direction);
+                                    }
+                                    values.push(value);
+                                }
+                                from_attribute_and_object = Bound::Excluded((attribute, value, Direction::Descending.first_id()));
                             }
+                            let values_objects = values
+                                .into_iter()
+                                .map(|value| self.[<$schema_name _attribute_value_objects>](&range.attribute, value, start_after_object.clone(), direction))
+                                .collect::<Result<Vec<_>>>()?;
+                            Ok(Box::new($crate::set_operations::Union::new(values_objects, direction)))
                         }
-                        if !self.index_config.maintain_only_tag_and_object_table {
-                            self.database_transaction
+
+                        fn [<$schema_name _sorted_attribute_range_objects>](
+                            &self,
+                            range: &$crate::AttributeRange,
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let from_attribute_and_object = (range.attribute.clone(), range.first_value(), Id::default());
+                            let mut objects = self.database_transaction
                                 .$schema_name
-                                .object
-                                .insert(object.clone(), ());
+                                .attribute_and_object
+                                .iter(Bound::Included(&from_attribute_and_object), false).with_context(|| format!("Can not initiate iteration over attribute_and_object table starting from key {from_attribute_and_object:?}"))?
+                                .map(|(key, _)| Ok(key))
+                                .take_while(|(attribute, value, _)| Ok(*attribute == range.attribute && !range.is_past_end(value)))
+                                .filter(|(_, value, _)| Ok(range.contains(value)))
+                                .map(|(_, _, object)| Ok(object))
+                                .filter(|object| Ok(start_after_object.as_ref().is_none_or(|start_after_object| direction.precedes(start_after_object, object))))
+                                .collect::<Vec<_>>()
+                                .with_context(|| format!("Can not get objects having attribute in range {range:?}"))?;
+                            objects.sort_by(|left, right| if direction.is_descending() { right.cmp(left) } else { left.cmp(right) });
+                            Ok(Box::new($crate::fallible_iterator::convert(objects.into_iter().map(Ok))))
                         }
-                        Ok(self)
-                    }
 
-                    pub fn [<$schema_name _remove_object>](&mut self, object: &Id) -> Result<&mut Self> {
-                        for tag in self.[<$schema_name _get_tags>](object)? {
-                            self.database_transaction
-                                .$schema_name
-                                .tag_and_object
-                                .remove(&(tag.clone(), object.clone()));
-                            if !self.index_config.maintain_only_tag_and_object_table {
+                        // distinct objects of object_and_attribute table, which are not listed in object table unless tagged
+                        fn [<$schema_name _attributed_objects>](
+                            &self,
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let from_object_and_attribute = start_after_object.map(|object| {
+                                let attribute = if direction.is_descending() { Id::default() } else { Direction::Descending.first_id() };
+                                (object, attribute)
+                            });
+                            let mut previous_object = None;
+                            Ok(Box::new(
                                 self.database_transaction
                                     .$schema_name
-                                    .object_and_tag
-                                    .remove(&(object.clone(), tag.clone()));
-                            }
+                                    .object_and_attribute
+                                    .iter(
+                                        if let Some(from_object_and_attribute) = &from_object_and_attribute {
+                                            Bound::Excluded(from_object_and_attribute)
+                                        } else {
+                                            Bound::Unbounded
+                                        },
+                                        direction.is_descending()
+                                    ).with_context(|| format!("Can not initiate iteration over object_and_attribute table starting after key {from_object_and_attribute:?}"))?
+                                    .map(|((object, _), _)| Ok(object))
+                                    .filter(move |object| {
+                                        let is_new = previous_object.as_ref() != Some(object);
+                                        previous_object = Some(object.clone());
+                                        Ok(is_new)
+                                    }),
+                            ))
                         }
-                        if !self.index_config.maintain_only_tag_and_object_table {
-                            self.database_transaction
+
+                        // None for schemas written before layout table was introduced
+                        pub fn [<$schema_name _layout>](&self) -> Result<Option<$crate::SchemaLayout>> {
+                            Ok(self
+                                .database_transaction
                                 .$schema_name
-                                .object
-                                .remove(object);
+                                .layout
+                                .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over layout table")?
+                                .next()?
+                                .map(|(_, layout)| layout))
                         }
-                        Ok(self)
-                    }
 
-                    pub fn [<$schema_name _remove_tags_from_object>](
-                        &mut self,
-                        object: &Id,
-                        tags: &[Id],
-                    ) -> Result<&mut Self> {
-                        for tag in tags {
-                            self.database_transaction
+                        fn [<$schema_name _stored_tag_count>](&self, tag: &Id) -> Result<u64> {
+                            Ok(self
+                                .database_transaction
                                 .$schema_name
-                                .tag_and_object
-                                .remove(&(tag.clone(), object.clone()));
-                            if !self.index_config.maintain_only_tag_and_object_table {
-                                self.database_transaction
-                                    .$schema_name
-                                    .object_and_tag
-                                    .remove(&(object.clone(), tag.clone()));
+                                .tag_count
+                                .iter(Bound::Included(tag), false).with_context(|| format!("Can not initiate iteration over tag_count table starting from key {tag:?}"))?
+                                .next()?
+                                .filter(|(current_tag, _)| current_tag == tag)
+                                .map(|(_, count)| count)
+                                .unwrap_or(0))
+                        }
+
+                        pub fn [<$schema_name _tag_cardinality>](&self, tag: &Id) -> Result<u64> {
+                            if self.index_config.maintain_tag_count_table {
+                                self.[<$schema_name _stored_tag_count>](tag)
+                            } else {
+                                Ok(self.[<$schema_name _tag_objects>](tag, None, Direction::Ascending)?.count()? as u64)
                             }
                         }
-                        if (!self.index_config.maintain_only_tag_and_object_table &&
-                            self.database_transaction
-                                .$schema_name
-                                .object_and_tag
-                                .iter(Bound::Included(&(object.clone(), Id::default())), false)?
-                                .take_while(|((object_left, _), _)| Ok(object_left == object))
-                                .next()?.is_none())
-                        {
-                            self.database_transaction
-                                .$schema_name
-                                .object
-                                .remove(object);
+
+                        // without tag_count table rows read to estimate cardinalities are taken from estimation budget, shared by the
+                        // tags evenly with rows left unread by one tag given to the next ones, so that searching with many tags does
+                        // not read CARDINALITY_ESTIMATION_LIMIT rows for each of them; every tag reads at least one row
+                        fn [<$schema_name _estimated_tag_cardinalities>](&self, tags: &[Id], estimation_budget: &mut usize) -> Result<Vec<u64>> {
+                            let mut result = Vec::with_capacity(tags.len());
+                            for (i, tag) in tags.iter().enumerate() {
+                                result.push(if self.index_config.maintain_tag_count_table {
+                                    self.[<$schema_name _stored_tag_count>](tag)?
+                                } else {
+                                    let limit = (*estimation_budget / (tags.len() - i)).clamp(1, CARDINALITY_ESTIMATION_LIMIT);
+                                    let read = self.[<$schema_name _tag_objects>](tag, None, Direction::Ascending)?.take(limit).count()?;
+                                    *estimation_budget = estimation_budget.saturating_sub(read);
+                                    read as u64
+                                });
+                            }
+                            Ok(result)
                         }
-                        Ok(self)
-                    }
-                }
-            )+
-        }
 
-        $(
-            paste! {
-                struct [<$schema_name:camel Cursor>]<'a> {
-                    iterator: Box<dyn FallibleIterator<Item = ((Id, Id), ()), Error = Error> + 'a>,
-                    current_value: Option<(Id, Id)>,
-                }
+                        fn [<$schema_name _sorted_by_selectivity>](&self, tags: &[Id], estimation_budget: &mut usize) -> Result<Vec<(u64, Id)>> {
+                            let mut tags_with_cardinalities = self.[<$schema_name _estimated_tag_cardinalities>](tags, estimation_budget)?
+                                .into_iter()
+                                .zip(tags.iter().cloned())
+                                .collect::<Vec<_>>();
+                            tags_with_cardinalities.sort_by_key(|(cardinality, _)| *cardinality);
+                            Ok(tags_with_cardinalities)
+                        }
 
-                impl<'a> [<$schema_name:camel Cursor>]<'a> {
-                    fn new(
-                        mut iterator: Box<dyn FallibleIterator<Item = ((Id, Id), ()), Error = Error> + 'a>,
-                    ) -> Result<Self> {
-                        let current_value = iterator
-                            .next().with_context(|| "Can not get first value from iterator")?
-                            .and_then(|(current_value, _)| Some(current_value));
-                        Ok(Self {
-                            iterator,
-                            current_value,
-                        })
-                    }
+                        fn [<$schema_name _tag_objects>](
+                            &self,
+                            tag: &Id,
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let search_tag_id = tag.clone();
+                            let from_tag_and_object = (search_tag_id.clone(), start_after_object.clone().unwrap_or(direction.first_id()));
+                            Ok(Box::new(
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .iter(Bound::Included(&from_tag_and_object), direction.is_descending()).with_context(|| format!("Can not initiate iteration over tag_and_object table starting from key {from_tag_and_object:?}"))?
+                                    .map(|((tag_id, object_id), _)| Ok((tag_id, object_id)))
+                                    .take_while(move |(tag_id, _)| Ok(tag_id == &search_tag_id))
+                                    .map(|(_, object_id)| Ok(object_id))
+                                    .filter(move |object_id| Ok(start_after_object.is_none() || *object_id != from_tag_and_object.1)),
+                            ))
+                        }
 
-                    fn next(&mut self) -> Result<()> {
-                        self.current_value = self
-                            .iterator
-                            .next().with_context(|| format!("Can not get next value from iterator after value {:?}", self.current_value))?
-                            .and_then(|(current_value, _)| Some(current_value));
-                        Ok(())
-                    }
-                }
+                        // tags in use having names which start with given prefix, in names order
+                        pub fn [<$schema_name _tag_dictionary_entries>](
+                            &self,
+                            prefix: &[u8],
+                        ) -> Result<Box<dyn FallibleIterator<Item = (Vec<u8>, Id), Error = Error> + '_>> {
+                            let from_tag_dictionary = (prefix.to_vec(), Id::default());
+                            let prefix = prefix.to_vec();
+                            Ok(Box::new(
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_dictionary
+                                    .iter(Bound::Included(&from_tag_dictionary), false).with_context(|| format!("Can not initiate iteration over tag_dictionary table starting from key {from_tag_dictionary:?}"))?
+                                    .map(|(key, _)| Ok(key))
+                                    .take_while(move |(name, _)| Ok(name.starts_with(&prefix)))
+                                    .filter(|(_, tag)| self.[<$schema_name _has_object_with_tag>](tag)),
+                            ))
+                        }
 
-                pub struct [<$schema_name:camel SearchIterator>]<'a> {
-                    database_transaction: &'a lawn_database::TablesTransactions,
-                    present_tags_ids: Vec<Id>,
-                    absent_tags_ids: Vec<Id>,
-                    start_after_object: Option<Id>,
-                    cursors: Vec<[<$schema_name:camel Cursor>]<'a>>,
-                    index_1: usize,
-                    index_2: usize,
-                    end: bool,
-                }
+                        // ranking by cardinality reads all the tags with given prefix
+                        pub fn [<$schema_name _suggest>](
+                            &self,
+                            prefix: &[u8],
+                            limit: usize,
+                            ranked: bool,
+                        ) -> Result<Vec<(Vec<u8>, Id)>> {
+                            let entries = self.[<$schema_name _tag_dictionary_entries>](prefix)?;
+                            if !ranked {
+                                return entries
+                                    .take(limit)
+                                    .collect::<Vec<_>>()
+                                    .with_context(|| format!("Can not suggest {limit} tags with prefix {prefix:?}"));
+                            }
+                            let mut entries_with_cardinalities = entries
+                                .map(|(name, tag)| Ok((self.[<$schema_name _tag_cardinality>](&tag)?, name, tag)))
+                                .collect::<Vec<_>>()
+                                .with_context(|| format!("Can not get cardinalities of tags with prefix {prefix:?}"))?;
+                            entries_with_cardinalities.sort_by(|(left_cardinality, left_name, _), (right_cardinality, right_name, _)| {
+                                right_cardinality.cmp(left_cardinality).then_with(|| left_name.cmp(right_name))
+                            });
+                            Ok(entries_with_cardinalities
+                                .into_iter()
+                                .take(limit)
+                                .map(|(_, name, tag)| (name, tag))
+                                .collect())
+                        }
 
-                impl<'a> FallibleIterator for [<$schema_name:camel SearchIterator>]<'a> {
-                    type Item = Id;
-                    type Error = Error;
+                        // absent tag is merged, i.e. checked by its own cursor advanced along with candidates, when its estimated
+                        // cardinality times CURSOR_STEPS_BEFORE_SEEK is at least candidates cardinality, and probed with point
+                        // lookup for each candidate otherwise; CARDINALITY_ESTIMATION_LIMIT stands for unknown candidates cardinality
+                        fn [<$schema_name _absent_tags_filter>](
+                            &self,
+                            absent_tags: &[Id],
+                            candidates_cardinality: Option<u64>,
+                            estimation_budget: &mut usize,
+                            direction: Direction,
+                        ) -> Result<[<$schema_name:camel AbsentTagsFilter>]<'_>> {
+                            let candidates_cardinality = candidates_cardinality.unwrap_or(CARDINALITY_ESTIMATION_LIMIT as u64);
+                            let absent_tags_cardinalities = self.[<$schema_name _estimated_tag_cardinalities>](absent_tags, estimation_budget)?;
+                            let mut strategies = Vec::with_capacity(absent_tags.len());
+                            for (absent_tag, absent_tag_cardinality) in absent_tags.iter().zip(absent_tags_cardinalities) {
+                                strategies.push(
+                                    if absent_tag_cardinality.saturating_mul(CURSOR_STEPS_BEFORE_SEEK as u64)
+                                        >= candidates_cardinality
+                                    {
+                                        [<$schema_name:camel AbsentTag>]::Merge {
+                                            tag: absent_tag.clone(),
+                                            cursor: [<$schema_name:camel Cursor>]::new(
+                                                self.database_transaction.deref(),
+                                                &(absent_tag.clone(), direction.first_id()),
+                                                direction,
+                                            )?,
+                                        }
+                                    } else {
+                                        [<$schema_name:camel AbsentTag>]::Probe(absent_tag.clone())
+                                    },
+                                );
+                            }
+                            Ok([<$schema_name:camel AbsentTagsFilter>] {
+                                database_transaction: self.database_transaction.deref(),
+                                absent_tags: strategies,
+                            })
+                        }
 
-                    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
-                        if self.end {
-                            return Ok(None);
+                        fn [<$schema_name _without_absent_tags>]<'c>(
+                            &'c self,
+                            objects: Box<dyn FallibleIterator<Item = Id, Error = Error> + 'c>,
+                            absent_tags: &[Id],
+                            candidates_cardinality: Option<u64>,
+                            estimation_budget: &mut usize,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + 'c>> {
+                            if absent_tags.is_empty() {
+                                return Ok(objects);
+                            }
+                            let mut absent_tags_filter =
+                                self.[<$schema_name _absent_tags_filter>](absent_tags, candidates_cardinality, estimation_budget, direction)?;
+                            Ok(Box::new(objects.filter(move |object_id| Ok(!absent_tags_filter.excludes(object_id)?))))
                         }
-                        loop {
-                            if self.cursors.len() == self.present_tags_ids.len() {
-                                let first_cursor_object = self.cursors[0].current_value.clone().unwrap().1;
-                                if self.cursors.iter().all(|cursor| {
-                                    cursor
-                                        .current_value
-                                        .clone()
-                                        .is_some_and(|current_value| current_value.1 == first_cursor_object)
-                                }) {
-                                    let result = if fallible_iterator::convert(
-                                        self.absent_tags_ids
-                                            .iter()
-                                            .map(|id| Result::<Id>::Ok(id.clone())),
-                                    )
-                                    .all(|tag_id| {
-                                        let key = &(tag_id.clone(), first_cursor_object.clone());
+
+                        pub fn [<$schema_name _search>](
+                            &self,
+                            present_tags: &[Id],
+                            absent_tags: &[Id],
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            self.[<$schema_name _search_with_attributes>](present_tags, absent_tags, &[], start_after_object, direction)
+                        }
+
+                        // attribute ranges are intersected with present tags, following them as their cardinalities are unknown;
+                        // without both all objects are searched
+                        pub fn [<$schema_name _search_with_attributes>](
+                            &self,
+                            present_tags: &[Id],
+                            absent_tags: &[Id],
+                            attribute_ranges: &[$crate::AttributeRange],
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            if !attribute_ranges.is_empty() && !self.index_config.maintain_attribute_tables {
+                                $crate::anyhow::bail!("Can not search by attribute ranges {attribute_ranges:?} as index does not maintain attribute tables");
+                            }
+                            let mut estimation_budget = CARDINALITY_ESTIMATION_BUDGET;
+                            Ok(match (present_tags.len(), attribute_ranges.len()) {
+                                (0, 0) => {
+                                    if self.index_config.maintain_only_tag_and_object_table {
+                                        return Err($crate::LeanModeError { operation: "Search without present tags" }.into());
+                                    }
+                                    let from_object = start_after_object.clone().unwrap_or_default();
+                                    let mut objects: $crate::set_operations::Objects<'_> = Box::new(
                                         self.database_transaction
                                             .$schema_name
-                                            .tag_and_object
-                                            .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))
-                                    })? {
-                                        Some(first_cursor_object)
-                                    } else {
-                                        None
-                                    };
-                                    self.cursors[0].next().with_context(|| format!("Can not get next value for first cursor after value {:?}", self.cursors[0].current_value))?;
-                                    if !self.cursors[0]
-                                        .current_value
-                                        .as_ref()
-                                        .is_some_and(|first_cursor_value| {
-                                            first_cursor_value.0 == self.present_tags_ids[0]
-                                        })
-                                    {
-                                        self.end = true;
+                                            .object
+                                            .iter(
+                                                if let Some(start_after_object) = &start_after_object {
+                                                    Bound::Excluded(start_after_object)
+                                                } else {
+                                                    Bound::Unbounded
+                                                },
+                                                direction.is_descending()
+                                            ).with_context(|| format!("Can not initiate iteration over object_to_tags_count table starting from key {from_object:?}"))?
+                                            .map(|(object_id, _)| Ok(object_id)),
+                                    );
+                                    if self.index_config.maintain_attribute_tables {
+                                        objects = Box::new($crate::set_operations::Union::new(
+                                            vec![objects, self.[<$schema_name _attributed_objects>](start_after_object, direction)?],
+                                            direction,
+                                        ));
                                     }
-                                    return Ok(result);
-                                }
-                            }
-
-                            if self.cursors.len() < self.present_tags_ids.len()
-                                && self.cursors.len() <= self.index_1
-                            {
-                                let from_tag_and_object = &(
-                                    self.present_tags_ids[self.index_1].clone(),
-                                    if self.index_1 == 0 {
-                                        self.start_after_object.clone().unwrap_or_default()
+                                    self.[<$schema_name _without_absent_tags>](
+                                        objects,
+                                        absent_tags,
+                                        None,
+                                        &mut estimation_budget,
+                                        direction,
+                                    )?
+                                },
+                                (1, 0) => self.[<$schema_name _without_absent_tags>](
+                                    self.[<$schema_name _tag_objects>](&present_tags[0], start_after_object, direction)?,
+                                    absent_tags,
+                                    if absent_tags.is_empty() {
+                                        None
                                     } else {
-                                        self.cursors
-                                            .last()
-                                            .unwrap()
-                                            .current_value
-                                            .clone()
-                                            .unwrap()
-                                            .1
+                                        self.[<$schema_name _estimated_tag_cardinalities>](present_tags, &mut estimation_budget)?.pop()
                                     },
-                                );
-                                let mut cursor =
-                                    [<$schema_name:camel Cursor>]::new(self.database_transaction.$schema_name.tag_and_object.iter(Bound::Included(from_tag_and_object), false).with_context(|| format!("Can not initiate iteration over tag_and_object table starting from key {from_tag_and_object:?}"))?)?;
-                                if self.index_1 == 0 && self.start_after_object.is_some() {
-                                    cursor.next().with_context(|| format!("Can not propagate newely created cursor further (even getting nothing) to skip current entry as start_after_object {:?} is provided", self.start_after_object))?;
-                                }
-                                if !cursor
-                                    .current_value
-                                    .as_ref()
-                                    .is_some_and(|first_cursor_value| {
-                                        first_cursor_value.0 == self.present_tags_ids[self.index_1]
+                                    &mut estimation_budget,
+                                    direction,
+                                )?,
+                                (0, 1) => self.[<$schema_name _without_absent_tags>](
+                                    self.[<$schema_name _attribute_range_objects>](&attribute_ranges[0], start_after_object, direction)?,
+                                    absent_tags,
+                                    None,
+                                    &mut estimation_budget,
+                                    direction,
+                                )?,
+                                _ => {
+                                    let present_tags_with_cardinalities = self.[<$schema_name _sorted_by_selectivity>](present_tags, &mut estimation_budget)?;
+                                    let candidates_cardinality = present_tags_with_cardinalities.first().map(|(cardinality, _)| *cardinality);
+                                    let mut terms = present_tags_with_cardinalities
+                                        .into_iter()
+                                        .map(|(_, tag)| [<$schema_name:camel SearchTerm>]::Tag(tag))
+                                        .collect::<Vec<_>>();
+                                    for range in attribute_ranges {
+                                        terms.push([<$schema_name:camel SearchTerm>]::Objects(Some(
+                                            self.[<$schema_name _attribute_range_objects>](range, start_after_object.clone(), direction)?,
+                                        )));
+                                    }
+                                    Box::new([<$schema_name:camel SearchIterator>] {
+                                        database_transaction: self.database_transaction.deref(),
+                                        absent_tags_filter: self.[<$schema_name _absent_tags_filter>](
+                                            absent_tags,
+                                            candidates_cardinality,
+                                            &mut estimation_budget,
+                                            direction,
+                                        )?,
+                                        terms,
+                                        start_after_object,
+                                        direction,
+                                        cursors: Vec::new(),
+                                        index_1: 0 as usize,
+                                        index_2: 1 as usize,
+                                        end: false,
                                     })
-                                {
-                                    self.end = true;
-                                    return Ok(None);
                                 }
-                                self.cursors.push(cursor);
-                            }
+                            })
+                        }
 
-                            if self.cursors.len() < self.present_tags_ids.len()
-                                && self.cursors.len() <= self.index_2
-                            {
-                                let from_tag_and_object = &(
-                                    self.present_tags_ids[self.index_2].clone(),
-                                    self.cursors
-                                        .last()
-                                        .unwrap()
-                                        .current_value
-                                        .clone()
-                                        .unwrap()
-                                        .1,
-                                );
-                                let cursor = [<$schema_name:camel Cursor>]::new(
-                                    self.database_transaction.$schema_name.tag_and_object.iter(Bound::Included(from_tag_and_object), false).with_context(|| format!("Can not initiate iteration over tag_and_object table starting with key {from_tag_and_object:?}"))?,
-                                )?;
-                                if !cursor
-                                    .current_value
-                                    .as_ref()
-                                    .is_some_and(|first_cursor_value| {
-                                        first_cursor_value.0 == self.present_tags_ids[self.index_2]
-                                    })
-                                {
-                                    self.end = true;
-                                    return Ok(None);
-                                }
-                                self.cursors.push(cursor);
+                        pub fn [<$schema_name _search_token>](
+                            &self,
+                            present_tags: &[Id],
+                            absent_tags: &[Id],
+                            direction: Direction,
+                        ) -> $crate::SearchCursorToken {
+                            $crate::SearchCursorToken::new(stringify!($schema_name), present_tags, absent_tags, direction)
+                        }
+
+                        pub fn [<$schema_name _search_token_with_attributes>](
+                            &self,
+                            present_tags: &[Id],
+                            absent_tags: &[Id],
+                            attribute_ranges: &[$crate::AttributeRange],
+                            direction: Direction,
+                        ) -> $crate::SearchCursorToken {
+                            self.[<$schema_name _search_token>](present_tags, absent_tags, direction).with_attribute_ranges(attribute_ranges)
+                        }
+
+                        pub fn [<$schema_name _search_by_token>](
+                            &self,
+                            token: &$crate::SearchCursorToken,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            token.check_schema(stringify!($schema_name))?;
+                            self.[<$schema_name _search_with_attributes>](
+                                &token.present_tags,
+                                &token.absent_tags,
+                                &token.attribute_ranges,
+                                token.last_object.clone(),
+                                token.direction,
+                            )
+                        }
+
+                        // returns next page token only if there are objects left after the page
+                        pub fn [<$schema_name _search_page>](
+                            &self,
+                            token: &$crate::SearchCursorToken,
+                            limit: usize,
+                        ) -> Result<(Vec<Id>, Option<$crate::SearchCursorToken>)> {
+                            if limit == 0 {
+                                return Ok((Vec::new(), Some(token.clone())));
+                            }
+                            let mut page = self.[<$schema_name _search_by_token>](token)?
+                                .take(limit.saturating_add(1))
+                                .collect::<Vec<_>>()
+                                .with_context(|| format!("Can not get search page of size {limit} for token {token:?}"))?;
+                            if page.len() > limit {
+                                page.truncate(limit);
+                                let next_token = page.last().map(|last_object| token.resumed_after(last_object.clone()));
+                                Ok((page, next_token))
+                            } else {
+                                Ok((page, None))
                             }
+                        }
 
-                            while self.cursors[self.index_2].current_value.as_ref().unwrap().1
-                                < self.cursors[self.index_1].current_value.as_ref().unwrap().1
-                            {
-                                self.cursors[self.index_2].next().with_context(|| format!("Can not propagate {:?}-th cursor further", self.index_2 + 1))?;
-                                if !self.cursors[self.index_2]
-                                    .current_value
-                                    .as_ref()
-                                    .is_some_and(|current_value| {
-                                        current_value.0 == self.present_tags_ids[self.index_2]
-                                    })
-                                {
-                                    self.end = true;
-                                    return Ok(None);
+                        pub fn [<$schema_name _count>](&self, present_tags: &[Id], absent_tags: &[Id]) -> Result<usize> {
+                            self.[<$schema_name _search>](present_tags, absent_tags, None, Direction::Ascending)?
+                                .count()
+                                .with_context(|| format!("Can not count objects with tags {present_tags:?} and without tags {absent_tags:?}"))
+                        }
+
+                        pub fn [<$schema_name _count_with_limit>](
+                            &self,
+                            present_tags: &[Id],
+                            absent_tags: &[Id],
+                            limit: usize,
+                        ) -> Result<usize> {
+                            self.[<$schema_name _search>](present_tags, absent_tags, None, Direction::Ascending)?
+                                .take(limit)
+                                .count()
+                                .with_context(|| format!("Can not count up to {limit} objects with tags {present_tags:?} and without tags {absent_tags:?}"))
+                        }
+
+                        pub fn [<$schema_name _query>](
+                            &self,
+                            query: &$crate::Query,
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            Ok(match query {
+                                $crate::Query::Tag(tag) => self.[<$schema_name _search>](std::slice::from_ref(tag), &[], start_after_object, direction)?,
+                                $crate::Query::Or(subqueries) => Box::new($crate::set_operations::Union::new(
+                                    subqueries
+                                        .iter()
+                                        .map(|subquery| self.[<$schema_name _query>](subquery, start_after_object.clone(), direction))
+                                        .collect::<Result<Vec<_>>>()?,
+                                    direction,
+                                )),
+                                // tags of conjunction are searched at once, negated ones excluded by absent tags filter, so whole object
+                                // table is scanned only for conjunction having neither present tags nor included queries, as standalone negation
+                                $crate::Query::And(_) | $crate::Query::Not(_) => {
+                                    let $crate::QueryConjuncts { present_tags, absent_tags, included, excluded } = query.conjuncts();
+                                    let mut result = if !present_tags.is_empty() || included.is_empty() {
+                                        let mut intersected = vec![self.[<$schema_name _search>](&present_tags, &absent_tags, start_after_object.clone(), direction)?];
+                                        for subquery in included {
+                                            intersected.push(self.[<$schema_name _query>](subquery, start_after_object.clone(), direction)?);
+                                        }
+                                        if intersected.len() == 1 {
+                                            intersected.pop().unwrap()
+                                        } else {
+                                            Box::new($crate::set_operations::Intersection::new(intersected, direction))
+                                        }
+                                    } else {
+                                        let intersected = included
+                                            .into_iter()
+                                            .map(|subquery| self.[<$schema_name _query>](subquery, start_after_object.clone(), direction))
+                                            .collect::<Result<Vec<_>>>()?;
+                                        let mut estimation_budget = CARDINALITY_ESTIMATION_BUDGET;
+                                        self.[<$schema_name _without_absent_tags>](
+                                            if intersected.len() == 1 {
+                                                intersected.into_iter().next().unwrap()
+                                            } else {
+                                                Box::new($crate::set_operations::Intersection::new(intersected, direction))
+                                            },
+                                            &absent_tags,
+                                            None,
+                                            &mut estimation_budget,
+                                            direction,
+                                        )?
+                                    };
+                                    for subquery in excluded {
+                                        result = Box::new($crate::set_operations::Difference::new(
+                                            result,
+                                            self.[<$schema_name _query>](subquery, start_after_object.clone(), direction)?,
+                                            direction,
+                                        ));
+                                    }
+                                    result
                                 }
+                            })
+                        }
+
+                        // pairs are returned as (tag, object) in tag_and_object table order
+                        fn [<$schema_name _pairs_only_in_tag_and_object>](
+                            &self,
+                            start_after: Option<(Id, Id)>,
+                        ) -> Result<Box<dyn FallibleIterator<Item = (Id, Id), Error = Error> + '_>> {
+                            Ok(Box::new(self.database_transaction
+                                .$schema_name
+                                .tag_and_object
+                                .iter(
+                                    if let Some(start_after) = &start_after {
+                                        Bound::Excluded(start_after)
+                                    } else {
+                                        Bound::Unbounded
+                                    },
+                                    false
+                                ).with_context(|| format!("Can not initiate iteration over tag_and_object table starting after key {start_after:?}"))?
+                                .map(|(key, _)| Ok(key))
+                                .filter(|(tag, object)| Ok(!self.database_transaction
+                                    .$schema_name
+                                    .object_and_tag
+                                    .exists(&(object.clone(), tag.clone()))
+                                    .with_context(|| format!("Can not verify if key {:?} exists in object_and_tag table", (object, tag)))?))))
+                        }
+
+                        // pairs are returned as (tag, object) in object_and_tag table order
+                        fn [<$schema_name _pairs_only_in_object_and_tag>](
+                            &self,
+                            start_after: Option<(Id, Id)>,
+                        ) -> Result<Box<dyn FallibleIterator<Item = (Id, Id), Error = Error> + '_>> {
+                            let start_after = start_after.map(|(tag, object)| (object, tag));
+                            Ok(Box::new(self.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .iter(
+                                    if let Some(start_after) = &start_after {
+                                        Bound::Excluded(start_after)
+                                    } else {
+                                        Bound::Unbounded
+                                    },
+                                    false
+                                ).with_context(|| format!("Can not initiate iteration over object_and_tag table starting after key {start_after:?}"))?
+                                .map(|((object, tag), _)| Ok((tag, object)))
+                                .filter(|key| Ok(!self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .exists(key)
+                                    .with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?))))
+                        }
+
+                        fn [<$schema_name _listed_objects>](&self, start_after: &Option<Id>) -> Result<$crate::set_operations::Objects<'_>> {
+                            Ok(Box::new(self.database_transaction
+                                .$schema_name
+                                .object
+                                .iter(
+                                    if let Some(start_after) = start_after {
+                                        Bound::Excluded(start_after)
+                                    } else {
+                                        Bound::Unbounded
+                                    },
+                                    false
+                                ).with_context(|| format!("Can not initiate iteration over object table starting after key {start_after:?}"))?
+                                .map(|(object, _)| Ok(object))))
+                        }
+
+                        fn [<$schema_name _tagged_objects>](&self, start_after: &Option<Id>) -> Result<$crate::set_operations::Objects<'_>> {
+                            let start_after = start_after.clone().map(|object| (object, Direction::Descending.first_id()));
+                            let mut previous_object = None;
+                            Ok(Box::new(self.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .iter(
+                                    if let Some(start_after) = &start_after {
+                                        Bound::Excluded(start_after)
+                                    } else {
+                                        Bound::Unbounded
+                                    },
+                                    false
+                                ).with_context(|| format!("Can not initiate iteration over object_and_tag table starting after key {start_after:?}"))?
+                                .map(|((object, _), _)| Ok(object))
+                                .filter(move |object| {
+                                    let is_new = previous_object.as_ref() != Some(object);
+                                    previous_object = Some(object.clone());
+                                    Ok(is_new)
+                                })))
+                        }
+
+                        // objects from object table absent in object_and_tag table
+                        fn [<$schema_name _untagged_objects>](
+                            &self,
+                            start_after: Option<Id>,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            Ok(Box::new($crate::set_operations::Difference::new(
+                                self.[<$schema_name _listed_objects>](&start_after)?,
+                                self.[<$schema_name _tagged_objects>](&start_after)?,
+                                Direction::Ascending,
+                            )))
+                        }
+
+                        // objects from object_and_tag table absent in object table
+                        fn [<$schema_name _missing_objects>](
+                            &self,
+                            start_after: Option<Id>,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            Ok(Box::new($crate::set_operations::Difference::new(
+                                self.[<$schema_name _tagged_objects>](&start_after)?,
+                                self.[<$schema_name _listed_objects>](&start_after)?,
+                                Direction::Ascending,
+                            )))
+                        }
+
+                        // findings are counted while streaming, keeping only samples of them
+                        pub fn [<$schema_name _verify>](&self) -> Result<$crate::SchemaVerificationReport> {
+                            if self.index_config.maintain_only_tag_and_object_table {
+                                return Err($crate::LeanModeError { operation: "Verifying index" }.into());
                             }
-                            if self.cursors[self.index_2].current_value.as_ref().unwrap().1
-                                == self.cursors[self.index_1].current_value.as_ref().unwrap().1
-                            {
-                                self.index_1 = (self.index_1 + 1) % self.present_tags_ids.len();
-                                self.index_2 = (self.index_2 + 1) % self.present_tags_ids.len();
+                            Ok($crate::SchemaVerificationReport {
+                                pairs_only_in_tag_and_object: $crate::Finding::from_items(self.[<$schema_name _pairs_only_in_tag_and_object>](None)?)
+                                    .with_context(|| "Can not find pairs present only in tag_and_object table")?,
+                                pairs_only_in_object_and_tag: $crate::Finding::from_items(self.[<$schema_name _pairs_only_in_object_and_tag>](None)?)
+                                    .with_context(|| "Can not find pairs present only in object_and_tag table")?,
+                                untagged_objects: $crate::Finding::from_items(self.[<$schema_name _untagged_objects>](None)?)
+                                    .with_context(|| "Can not find untagged objects in object table")?,
+                                missing_objects: $crate::Finding::from_items(self.[<$schema_name _missing_objects>](None)?)
+                                    .with_context(|| "Can not find tagged objects missing from object table")?,
+                            })
+                        }
+
+                        pub fn [<$schema_name _search_union>](
+                            &self,
+                            any_tags: &[Id],
+                            absent_tags: &[Id],
+                            start_after_object: Option<Id>,
+                            direction: Direction,
+                        ) -> Result<Box<dyn FallibleIterator<Item = Id, Error = Error> + '_>> {
+                            let objects = any_tags
+                                .iter()
+                                .map(|tag| self.[<$schema_name _tag_objects>](tag, start_after_object.clone(), direction))
+                                .collect::<Result<Vec<_>>>()?;
+                            let mut estimation_budget = CARDINALITY_ESTIMATION_BUDGET;
+                            self.[<$schema_name _without_absent_tags>](
+                                Box::new($crate::set_operations::Union::new(objects, direction)),
+                                absent_tags,
+                                None,
+                                &mut estimation_budget,
+                                direction,
+                            )
+                        }
+                    }
+                )+
+            };
+        }
+
+        impl<'a> ReadTransaction<'a> {
+            define_read_methods!();
+        }
+
+        impl<'a, 'b> WriteTransaction<'a, 'b> {
+            define_read_methods!();
+
+            $(
+                paste! {
+                    // tag_count table is filled from tag_and_object table when it was not maintained before, and cleared
+                    // when it is not maintained anymore, so that toggling maintain_tag_count_table keeps counts right
+                    fn [<$schema_name _migrate>](&mut self) -> Result<()> {
+                        let stored_layout = self.[<$schema_name _layout>]()?;
+                        // schemas written before layout table have object tables complete unless index maintained only tag_and_object table
+                        let mut layout = stored_layout.unwrap_or($crate::SchemaLayout {
+                            object_tables: if self.index_config.maintain_only_tag_and_object_table {
+                                $crate::ObjectTables::Absent
                             } else {
-                                while self.cursors[0].current_value.as_ref().unwrap().1
-                                    < self.cursors[self.index_2].current_value.as_ref().unwrap().1
+                                $crate::ObjectTables::Complete
+                            },
+                            ..Default::default()
+                        });
+                        if layout.version > $crate::LAYOUT_VERSION {
+                            $crate::anyhow::bail!(
+                                "Can not open schema {} of layout version {}, latest supported is {}",
+                                stringify!($schema_name),
+                                layout.version,
+                                $crate::LAYOUT_VERSION
+                            );
+                        }
+                        if !self.index_config.maintain_attribute_tables
+                            && self.database_transaction
+                                .$schema_name
+                                .attribute_and_object
+                                .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over attribute_and_object table")?
+                                .next()?
+                                .is_some()
+                        {
+                            $crate::anyhow::bail!(
+                                "Can not open schema {} without maintaining attribute tables as they hold attributes",
+                                stringify!($schema_name)
+                            );
+                        }
+                        if self.index_config.maintain_only_tag_and_object_table {
+                            layout.object_tables = $crate::ObjectTables::Absent;
+                        } else if layout.object_tables != $crate::ObjectTables::Complete {
+                            $crate::anyhow::bail!(
+                                "Can not open schema {} with object tables {:?}, open it maintaining only tag_and_object table and rebuild them",
+                                stringify!($schema_name),
+                                layout.object_tables
+                            );
+                        }
+                        if layout.maintains_tag_count_table != self.index_config.maintain_tag_count_table {
+                            let stale_tags = self.database_transaction
+                                .$schema_name
+                                .tag_count
+                                .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over tag_count table")?
+                                .map(|(tag, _)| Ok(tag))
+                                .collect::<Vec<_>>()?;
+                            for tag in stale_tags {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_count
+                                    .remove(&tag);
+                            }
+                            if self.index_config.maintain_tag_count_table {
+                                let mut tags_counts: Vec<(Id, u64)> = Vec::new();
                                 {
-                                    self.cursors[0].next().with_context(|| format!("Can not propagate first cursor further"))?;
-                                    if !self.cursors[0]
-                                        .current_value
-                                        .as_ref()
-                                        .is_some_and(|current_value| current_value.0 == self.present_tags_ids[0])
-                                    {
-                                        self.end = true;
-                                        return Ok(None);
+                                    let mut tags_and_objects = self.database_transaction
+                                        .$schema_name
+                                        .tag_and_object
+                                        .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over tag_and_object table")?;
+                                    while let Some(((tag, _), _)) = tags_and_objects.next()? {
+                                        match tags_counts.last_mut() {
+                                            Some((last_tag, count)) if *last_tag == tag => *count += 1,
+                                            _ => tags_counts.push((tag, 1)),
+                                        }
                                     }
                                 }
-                                self.index_1 = 0;
-                                self.index_2 = 1;
+                                for (tag, count) in tags_counts {
+                                    self.database_transaction
+                                        .$schema_name
+                                        .tag_count
+                                        .insert(tag, count);
+                                }
                             }
                         }
+                        layout.version = $crate::LAYOUT_VERSION;
+                        layout.maintains_tag_count_table = self.index_config.maintain_tag_count_table;
+                        if stored_layout != Some(layout) {
+                            self.database_transaction
+                                .$schema_name
+                                .layout
+                                .insert((), layout);
+                        }
+                        Ok(())
                     }
-                }
-            }
-        )+
-
-        impl Index {
-            pub fn new(config: IndexConfig) -> Result<Self> {
-                Ok(Self {
-                    database: lawn_database::Database::new(config.database.clone()).with_context(|| format!("Can not create dream index using database config {:?}", config.database))?,
-                    config
-                })
-            }
 
-            pub fn lock_all_and_write<F, R>(&mut self, mut f: F) -> Result<R>
-            where
-                F: FnMut(&mut WriteTransaction<'_, '_>) -> Result<R>,
-            {
-                self.database
-                    .lock_all_and_write(|database_write_transaction| {
-                        f(&mut WriteTransaction {
-                            database_transaction: database_write_transaction,
-                            index_config: &self.config
-                        })
-                    }).with_context(|| "Can not lock lawn database and initiate write transaction")
-            }
+                    // object tables are written to while complete or being rebuilt, even if not read from
+                    fn [<$schema_name _writes_object_tables>](&self) -> Result<bool> {
+                        if !self.index_config.maintain_only_tag_and_object_table {
+                            return Ok(true);
+                        }
+                        Ok(self
+                            .[<$schema_name _layout>]()?
+                            .is_some_and(|layout| layout.object_tables != $crate::ObjectTables::Absent))
+                    }
 
-            pub fn lock_all_writes_and_read<F, R>(&self, mut f: F) -> Result<R>
-            where
-                F: FnMut(ReadTransaction) -> Result<R>,
-            {
-                self.database
-                    .lock_all_writes_and_read(|database_read_transaction| {
-                        f(ReadTransaction {
-                            database_transaction: database_read_transaction,
+                    fn [<$schema_name _change_tag_count>](&mut self, tag: &Id, delta: i64) -> Result<()> {
+                        let count = self.[<$schema_name _stored_tag_count>](tag)?.saturating_add_signed(delta);
+                        if count == 0 {
+                            self.database_transaction
+                                .$schema_name
+                                .tag_count
+                                .remove(tag);
+                        } else {
+                            self.database_transaction
+                                .$schema_name
+                                .tag_count
+                                .insert(tag.clone(), count);
+                        }
+                        Ok(())
+                    }
+
+                    pub fn [<$schema_name _insert>](&mut self, object: &Id, tags: &[Id]) -> Result<&mut Self> {
+                        let writes_object_tables = self.[<$schema_name _writes_object_tables>]()?;
+                        for tag in tags {
+                            let key = &(tag.clone(), object.clone());
+                            if self.index_config.maintain_tag_count_table
+                                && !self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
+                            {
+                                self.[<$schema_name _change_tag_count>](tag, 1)?;
+                            }
+                            self.database_transaction
+                                .$schema_name
+                                .tag_and_object
+                                .insert((tag.clone(), object.clone()), ());
+                            if writes_object_tables {
+                                self.database_transaction
+                                    .$schema_name
+                                    .object_and_tag
+                                    .insert((object.clone(), tag.clone()), ());
+                            }
+                        }
+                        if writes_object_tables {
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .insert(object.clone(), ());
+                        }
+                        Ok(self)
+                    }
+
+                    pub fn [<$schema_name _insert_named>]<N: AsRef<[u8]>>(
+                        &mut self,
+                        object: &Id,
+                        named_tags: &[(N, Id)],
+                    ) -> Result<&mut Self> {
+                        self.[<$schema_name _insert>](object, &named_tags.iter().map(|(_, tag)| tag.clone()).collect::<Vec<_>>())?;
+                        self.[<$schema_name _register_tag_names>](named_tags)
+                    }
+
+                    // names tags without tagging anything, so tags inserted as raw identifiers can be named later
+                    pub fn [<$schema_name _register_tag_names>]<N: AsRef<[u8]>>(&mut self, named_tags: &[(N, Id)]) -> Result<&mut Self> {
+                        for (name, tag) in named_tags {
+                            if self.index_config.maintain_tag_dictionary_table
+                                && self.index_config.maintain_tag_name_table
+                                && let Some(previous_name) = self.[<$schema_name _get_tag_name>](tag)?
+                                && previous_name != name.as_ref()
+                            {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_dictionary
+                                    .remove(&(previous_name, tag.clone()));
+                            }
+                            if self.index_config.maintain_tag_dictionary_table {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_dictionary
+                                    .insert((name.as_ref().to_vec(), tag.clone()), ());
+                            }
+                            if self.index_config.maintain_tag_name_table {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_name
+                                    .insert(tag.clone(), name.as_ref().to_vec());
+                            }
+                        }
+                        Ok(self)
+                    }
+
+                    // pairs are sorted and deduplicated per table so that each table is loaded in key order
+                    pub fn [<$schema_name _insert_many>]<I>(&mut self, objects_with_tags: I) -> Result<usize>
+                    where
+                        I: IntoIterator<Item = (Id, Vec<Id>)>,
+                    {
+                        let mut tags_and_objects = Vec::new();
+                        let mut objects = Vec::new();
+                        for (object, tags) in objects_with_tags {
+                            // objects given without tags are skipped, as an object left with no tags by set_tags is removed
+                            if tags.is_empty() {
+                                continue;
+                            }
+                            tags_and_objects.extend(tags.into_iter().map(|tag| (tag, object.clone())));
+                            objects.push(object);
+                        }
+                        tags_and_objects.sort();
+                        tags_and_objects.dedup();
+
+                        let mut new_tags_and_objects = Vec::with_capacity(tags_and_objects.len());
+                        for key in tags_and_objects {
+                            if !self.database_transaction
+                                .$schema_name
+                                .tag_and_object
+                                .exists(&key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
+                            {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .insert(key.clone(), ());
+                                new_tags_and_objects.push(key);
+                            }
+                        }
+
+                        if self.index_config.maintain_tag_count_table {
+                            for tag_objects in new_tags_and_objects.chunk_by(|(left_tag, _), (right_tag, _)| left_tag == right_tag) {
+                                self.[<$schema_name _change_tag_count>](&tag_objects[0].0, tag_objects.len() as i64)?;
+                            }
+                        }
+
+                        if self.[<$schema_name _writes_object_tables>]()? {
+                            let mut new_objects_and_tags = new_tags_and_objects
+                                .iter()
+                                .map(|(tag, object)| (object.clone(), tag.clone()))
+                                .collect::<Vec<_>>();
+                            new_objects_and_tags.sort();
+                            for key in new_objects_and_tags {
+                                self.database_transaction
+                                    .$schema_name
+                                    .object_and_tag
+                                    .insert(key, ());
+                            }
+                            objects.sort();
+                            objects.dedup();
+                            for object in objects {
+                                self.database_transaction
+                                    .$schema_name
+                                    .object
+                                    .insert(object, ());
+                            }
+                        }
+                        Ok(new_tags_and_objects.len())
+                    }
+
+                    pub fn [<$schema_name _set_tags>](&mut self, object: &Id, tags: &[Id]) -> Result<$crate::TagsDiff> {
+                        let current_tags = std::collections::BTreeSet::from_iter(self.[<$schema_name _get_tags>](object)?);
+                        let new_tags = std::collections::BTreeSet::from_iter(tags.iter().cloned());
+                        let result = $crate::TagsDiff {
+                            added: new_tags.difference(&current_tags).cloned().collect(),
+                            removed: current_tags.difference(&new_tags).cloned().collect(),
+                        };
+                        if !result.removed.is_empty() {
+                            self.[<$schema_name _remove_tags_from_object>](object, &result.removed)?;
+                        }
+                        if !result.added.is_empty() {
+                            self.[<$schema_name _insert>](object, &result.added)?;
+                        }
+                        if new_tags.is_empty() && !self.index_config.maintain_only_tag_and_object_table {
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .remove(object);
+                        }
+                        Ok(result)
+                    }
+
+                    // replaces previous value of the attribute, if any
+                    pub fn [<$schema_name _set_attribute>]<V: $crate::AttributeValue>(
+                        &mut self,
+                        object: &Id,
+                        attribute: &Id,
+                        value: V,
+                    ) -> Result<&mut Self> {
+                        if !self.index_config.maintain_attribute_tables {
+                            $crate::anyhow::bail!("Can not set attribute {attribute:?} of object {object:?} as index does not maintain attribute tables");
+                        }
+                        self.[<$schema_name _remove_attribute>](object, attribute)?;
+                        let value = value.to_ordered_bytes();
+                        self.database_transaction
+                            .$schema_name
+                            .attribute_and_object
+                            .insert((attribute.clone(), value, object.clone()), ());
+                        self.database_transaction
+                            .$schema_name
+                            .object_and_attribute
+                            .insert((object.clone(), attribute.clone()), value);
+                        Ok(self)
+                    }
+
+                    pub fn [<$schema_name _remove_attribute>](&mut self, object: &Id, attribute: &Id) -> Result<bool> {
+                        if !self.index_config.maintain_attribute_tables {
+                            return Ok(false);
+                        }
+                        let Some(value) = self.[<$schema_name _get_encoded_attribute>](object, attribute)? else {
+                            return Ok(false);
+                        };
+                        self.database_transaction
+                            .$schema_name
+                            .attribute_and_object
+                            .remove(&(attribute.clone(), value, object.clone()));
+                        self.database_transaction
+                            .$schema_name
+                            .object_and_attribute
+                            .remove(&(object.clone(), attribute.clone()));
+                        Ok(true)
+                    }
+
+                    pub fn [<$schema_name _remove_object>](&mut self, object: &Id) -> Result<&mut Self> {
+                        // tags are got first, so nothing is removed if they can not be got in lean mode
+                        let tags = self.[<$schema_name _get_tags>](object)?;
+                        if self.index_config.maintain_attribute_tables {
+                            for (attribute, _) in self.[<$schema_name _get_attributes>](object)? {
+                                self.[<$schema_name _remove_attribute>](object, &attribute)?;
+                            }
+                        }
+                        for tag in tags {
+                            if self.index_config.maintain_tag_count_table {
+                                self.[<$schema_name _change_tag_count>](&tag, -1)?;
+                            }
+                            self.database_transaction
+                                .$schema_name
+                                .tag_and_object
+                                .remove(&(tag.clone(), object.clone()));
+                            if !self.index_config.maintain_only_tag_and_object_table {
+                                self.database_transaction
+                                    .$schema_name
+                                    .object_and_tag
+                                    .remove(&(object.clone(), tag.clone()));
+                            }
+                        }
+                        if !self.index_config.maintain_only_tag_and_object_table {
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .remove(object);
+                        }
+                        Ok(self)
+                    }
+
+                    pub fn [<$schema_name _remove_tags_from_object>](
+                        &mut self,
+                        object: &Id,
+                        tags: &[Id],
+                    ) -> Result<&mut Self> {
+                        let writes_object_tables = self.[<$schema_name _writes_object_tables>]()?;
+                        for tag in tags {
+                            let key = &(tag.clone(), object.clone());
+                            if self.index_config.maintain_tag_count_table
+                                && self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
+                            {
+                                self.[<$schema_name _change_tag_count>](tag, -1)?;
+                            }
+                            self.database_transaction
+                                .$schema_name
+                                .tag_and_object
+                                .remove(&(tag.clone(), object.clone()));
+                            if writes_object_tables {
+                                self.database_transaction
+                                    .$schema_name
+                                    .object_and_tag
+                                    .remove(&(object.clone(), tag.clone()));
+                            }
+                        }
+                        if writes_object_tables {
+                            self.[<$schema_name _remove_object_if_untagged>](object)?;
+                        }
+                        Ok(self)
+                    }
+
+                    fn [<$schema_name _remove_object_if_untagged>](&mut self, object: &Id) -> Result<()> {
+                        if self.database_transaction
+                            .$schema_name
+                            .object_and_tag
+                            .iter(Bound::Included(&(object.clone(), Id::default())), false)?
+                            .take_while(|((object_left, _), _)| Ok(object_left == object))
+                            .next()?.is_none()
+                        {
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .remove(object);
+                        }
+                        Ok(())
+                    }
+
+                    // objects of the tag are taken in chunks, each from the start as previous one is already untagged
+                    fn [<$schema_name _tag_objects_chunk>](&self, tag: &Id) -> Result<Vec<Id>> {
+                        self.[<$schema_name _tag_objects>](tag, None, Direction::Ascending)?
+                            .take(TAG_OBJECTS_CHUNK_SIZE)
+                            .collect::<Vec<_>>()
+                            .with_context(|| format!("Can not get objects tagged with {tag:?}"))
+                    }
+
+                    /// Without `tag_name` table, dictionary entries of the tag are found by scanning whole `tag_dictionary` table,
+                    /// so it takes time proportional to count of named tags
+                    pub fn [<$schema_name _remove_tag>](&mut self, tag: &Id) -> Result<usize> {
+                        let writes_object_tables = self.[<$schema_name _writes_object_tables>]()?;
+                        let mut removed_count = 0;
+                        loop {
+                            let objects = self.[<$schema_name _tag_objects_chunk>](tag)?;
+                            for object in objects.iter() {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .remove(&(tag.clone(), object.clone()));
+                                if writes_object_tables {
+                                    self.database_transaction
+                                        .$schema_name
+                                        .object_and_tag
+                                        .remove(&(object.clone(), tag.clone()));
+                                    self.[<$schema_name _remove_object_if_untagged>](object)?;
+                                }
+                            }
+                            removed_count += objects.len();
+                            if objects.len() < TAG_OBJECTS_CHUNK_SIZE {
+                                break;
+                            }
+                        }
+                        if self.index_config.maintain_tag_count_table {
+                            self.database_transaction
+                                .$schema_name
+                                .tag_count
+                                .remove(tag);
+                        }
+                        self.[<$schema_name _remove_tag_names>](tag)?;
+                        Ok(removed_count)
+                    }
+
+                    fn [<$schema_name _remove_tag_names>](&mut self, tag: &Id) -> Result<()> {
+                        if self.index_config.maintain_tag_dictionary_table {
+                            let names = if self.index_config.maintain_tag_name_table {
+                                self.[<$schema_name _get_tag_name>](tag)?.into_iter().collect::<Vec<_>>()
+                            } else {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_dictionary
+                                    .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over tag_dictionary table")?
+                                    .filter(|((_, current_tag), _)| Ok(current_tag == tag))
+                                    .map(|((name, _), _)| Ok(name))
+                                    .collect::<Vec<_>>()?
+                            };
+                            for name in names {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_dictionary
+                                    .remove(&(name, tag.clone()));
+                            }
+                        }
+                        if self.index_config.maintain_tag_name_table {
+                            self.database_transaction
+                                .$schema_name
+                                .tag_name
+                                .remove(tag);
+                        }
+                        Ok(())
+                    }
+
+                    pub fn [<$schema_name _rebuild_object_tables_batch>](
+                        &mut self,
+                        start_after: Option<(Id, Id)>,
+                        batch_size: usize,
+                    ) -> Result<Option<(Id, Id)>> {
+                        if batch_size == 0 {
+                            $crate::anyhow::bail!("Can not rebuild object tables in batches of size 0");
+                        }
+                        let mut layout = self.[<$schema_name _layout>]()?.unwrap_or_default();
+                        if start_after.is_none() {
+                            if self.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over object_and_tag table")?
+                                .next()?
+                                .is_some()
+                                || self.database_transaction
+                                    .$schema_name
+                                    .object
+                                    .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over object table")?
+                                    .next()?
+                                    .is_some()
+                            {
+                                $crate::anyhow::bail!(
+                                    "Can not start rebuilding object tables of schema {} as they are not empty, drop them first",
+                                    stringify!($schema_name)
+                                );
+                            }
+                            layout.object_tables = $crate::ObjectTables::Building;
+                            self.database_transaction
+                                .$schema_name
+                                .layout
+                                .insert((), layout);
+                        } else if layout.object_tables != $crate::ObjectTables::Building {
+                            $crate::anyhow::bail!(
+                                "Can not continue rebuilding object tables of schema {} as they are {:?}",
+                                stringify!($schema_name),
+                                layout.object_tables
+                            );
+                        }
+                        let batch = self.database_transaction
+                            .$schema_name
+                            .tag_and_object
+                            .iter(
+                                if let Some(start_after) = &start_after {
+                                    Bound::Excluded(start_after)
+                                } else {
+                                    Bound::Unbounded
+                                },
+                                false
+                            ).with_context(|| format!("Can not initiate iteration over tag_and_object table starting after key {start_after:?}"))?
+                            .take(batch_size)
+                            .map(|(key, _)| Ok(key))
+                            .collect::<Vec<_>>()?;
+                        for (tag, object) in batch.iter() {
+                            self.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .insert((object.clone(), tag.clone()), ());
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .insert(object.clone(), ());
+                        }
+                        if batch.len() < batch_size {
+                            layout.object_tables = $crate::ObjectTables::Complete;
+                            self.database_transaction
+                                .$schema_name
+                                .layout
+                                .insert((), layout);
+                            return Ok(None);
+                        }
+                        Ok(batch.last().cloned())
+                    }
+
+                    // returns true if both tables are empty after removing batch
+                    pub fn [<$schema_name _drop_object_tables_batch>](&mut self, batch_size: usize) -> Result<bool> {
+                        if batch_size == 0 {
+                            $crate::anyhow::bail!("Can not drop object tables in batches of size 0");
+                        }
+                        let mut layout = self.[<$schema_name _layout>]()?.unwrap_or_default();
+                        if layout.object_tables != $crate::ObjectTables::Absent {
+                            layout.object_tables = $crate::ObjectTables::Absent;
+                            self.database_transaction
+                                .$schema_name
+                                .layout
+                                .insert((), layout);
+                        }
+                        let object_and_tag_batch = self.database_transaction
+                            .$schema_name
+                            .object_and_tag
+                            .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over object_and_tag table")?
+                            .take(batch_size)
+                            .map(|(key, _)| Ok(key))
+                            .collect::<Vec<_>>()?;
+                        for key in object_and_tag_batch.iter() {
+                            self.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .remove(key);
+                        }
+                        let object_batch = self.database_transaction
+                            .$schema_name
+                            .object
+                            .iter(Bound::Unbounded, false).with_context(|| "Can not initiate iteration over object table")?
+                            .take(batch_size - object_and_tag_batch.len())
+                            .map(|(object, _)| Ok(object))
+                            .collect::<Vec<_>>()?;
+                        for object in object_batch.iter() {
+                            self.database_transaction
+                                .$schema_name
+                                .object
+                                .remove(object);
+                        }
+                        Ok(object_and_tag_batch.len() + object_batch.len() < batch_size)
+                    }
+
+                    // tag_and_object table is trusted, so pairs are repaired first and objects are verified against repaired object_and_tag table;
+                    // inconsistencies are repaired in chunks, so memory used does not grow with their count
+                    pub fn [<$schema_name _repair>](&mut self) -> Result<$crate::SchemaVerificationReport> {
+                        if self.index_config.maintain_only_tag_and_object_table {
+                            return Err($crate::LeanModeError { operation: "Repairing index" }.into());
+                        }
+                        let pairs_only_in_tag_and_object = $crate::Finding::from_repaired_chunks(
+                            self,
+                            |transaction, start_after| transaction.[<$schema_name _pairs_only_in_tag_and_object>](start_after),
+                            |transaction, (tag, object)| transaction.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .insert((object.clone(), tag.clone()), ()),
+                        ).with_context(|| "Can not repair pairs present only in tag_and_object table")?;
+                        let pairs_only_in_object_and_tag = $crate::Finding::from_repaired_chunks(
+                            self,
+                            |transaction, start_after| transaction.[<$schema_name _pairs_only_in_object_and_tag>](start_after),
+                            |transaction, (tag, object)| transaction.database_transaction
+                                .$schema_name
+                                .object_and_tag
+                                .remove(&(object.clone(), tag.clone())),
+                        ).with_context(|| "Can not repair pairs present only in object_and_tag table")?;
+                        let untagged_objects = $crate::Finding::from_repaired_chunks(
+                            self,
+                            |transaction, start_after| transaction.[<$schema_name _untagged_objects>](start_after),
+                            |transaction, object| transaction.database_transaction
+                                .$schema_name
+                                .object
+                                .remove(object),
+                        ).with_context(|| "Can not repair untagged objects in object table")?;
+                        let missing_objects = $crate::Finding::from_repaired_chunks(
+                            self,
+                            |transaction, start_after| transaction.[<$schema_name _missing_objects>](start_after),
+                            |transaction, object| transaction.database_transaction
+                                .$schema_name
+                                .object
+                                .insert(object.clone(), ()),
+                        ).with_context(|| "Can not repair tagged objects missing from object table")?;
+                        Ok($crate::SchemaVerificationReport {
+                            pairs_only_in_tag_and_object,
+                            pairs_only_in_object_and_tag,
+                            untagged_objects,
+                            missing_objects,
                         })
-                    }).with_context(|| "Can not lock all write operations on lawn database and initiate read transaction")
-            }
+                    }
+
+                    /// Names of `from` tag are removed along with it, as `into` tag keeps its own name
+                    pub fn [<$schema_name _merge_tags>](&mut self, from: &Id, into: &Id) -> Result<usize> {
+                        if from == into {
+                            return Ok(0);
+                        }
+                        let writes_object_tables = self.[<$schema_name _writes_object_tables>]()?;
+                        let mut merged_count = 0;
+                        let mut added_count = 0;
+                        loop {
+                            let objects = self.[<$schema_name _tag_objects_chunk>](from)?;
+                            for object in objects.iter() {
+                                self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .remove(&(from.clone(), object.clone()));
+                                if writes_object_tables {
+                                    self.database_transaction
+                                        .$schema_name
+                                        .object_and_tag
+                                        .remove(&(object.clone(), from.clone()));
+                                }
+                                let key = &(into.clone(), object.clone());
+                                if !self.database_transaction
+                                    .$schema_name
+                                    .tag_and_object
+                                    .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
+                                {
+                                    self.database_transaction
+                                        .$schema_name
+                                        .tag_and_object
+                                        .insert(key.clone(), ());
+                                    if writes_object_tables {
+                                        self.database_transaction
+                                            .$schema_name
+                                            .object_and_tag
+                                            .insert((object.clone(), into.clone()), ());
+                                    }
+                                    added_count += 1;
+                                }
+                            }
+                            merged_count += objects.len();
+                            if objects.len() < TAG_OBJECTS_CHUNK_SIZE {
+                                break;
+                            }
+                        }
+                        if self.index_config.maintain_tag_count_table {
+                            self.database_transaction
+                                .$schema_name
+                                .tag_count
+                                .remove(from);
+                            self.[<$schema_name _change_tag_count>](into, added_count)?;
+                        }
+                        self.[<$schema_name _remove_tag_names>](from)?;
+                        Ok(merged_count)
+                    }
+                }
+            )+
+        }
+
+        $(
+            paste! {
+                struct [<$schema_name:camel Cursor>]<'a> {
+                    database_transaction: &'a lawn_database::TablesTransactions,
+                    direction: Direction,
+                    iterator: Box<dyn FallibleIterator<Item = ((Id, Id), ()), Error = Error> + 'a>,
+                    current_value: Option<(Id, Id)>,
+                }
+
+                impl<'a> [<$schema_name:camel Cursor>]<'a> {
+                    fn new(
+                        database_transaction: &'a lawn_database::TablesTransactions,
+                        from_tag_and_object: &(Id, Id),
+                        direction: Direction,
+                    ) -> Result<Self> {
+                        let mut iterator = database_transaction
+                            .$schema_name
+                            .tag_and_object
+                            .iter(Bound::Included(from_tag_and_object), direction.is_descending()).with_context(|| format!("Can not initiate iteration over tag_and_object table starting from key {from_tag_and_object:?}"))?;
+                        let current_value = iterator
+                            .next().with_context(|| "Can not get first value from iterator")?
+                            .and_then(|(current_value, _)| Some(current_value));
+                        Ok(Self {
+                            database_transaction,
+                            direction,
+                            iterator,
+                            current_value,
+                        })
+                    }
+
+                    fn seek(&mut self, target: &(Id, Id)) -> Result<()> {
+                        *self = Self::new(self.database_transaction, target, self.direction).with_context(|| format!("Can not seek cursor from value {:?} to key {target:?}", self.current_value))?;
+                        Ok(())
+                    }
+
+                    // steps a few entries first as neighbouring keys are cheap to read, then re-seeks the
+                    // underlying iterator so that sparse-against-dense intersections skip the gap at once
+                    fn advance_to(&mut self, target: &(Id, Id)) -> Result<()> {
+                        for _ in 0..CURSOR_STEPS_BEFORE_SEEK {
+                            if !self.current_value.as_ref().is_some_and(|current_value| self.direction.precedes(current_value, target)) {
+                                return Ok(());
+                            }
+                            self.next()?;
+                        }
+                        if self.current_value.as_ref().is_some_and(|current_value| self.direction.precedes(current_value, target)) {
+                            self.seek(target)?;
+                        }
+                        Ok(())
+                    }
+
+                    fn next(&mut self) -> Result<()> {
+                        self.current_value = self
+                            .iterator
+                            .next().with_context(|| format!("Can not get next value from iterator after value {:?}", self.current_value))?
+                            .and_then(|(current_value, _)| Some(current_value));
+                        Ok(())
+                    }
+                }
+
+                enum [<$schema_name:camel AbsentTag>]<'a> {
+                    Probe(Id),
+                    Merge {
+                        tag: Id,
+                        cursor: [<$schema_name:camel Cursor>]<'a>,
+                    },
+                }
+
+                struct [<$schema_name:camel AbsentTagsFilter>]<'a> {
+                    database_transaction: &'a lawn_database::TablesTransactions,
+                    absent_tags: Vec<[<$schema_name:camel AbsentTag>]<'a>>,
+                }
+
+                impl<'a> [<$schema_name:camel AbsentTagsFilter>]<'a> {
+                    // candidates must be checked in search direction order for merge cursors to stay valid
+                    fn excludes(&mut self, object: &Id) -> Result<bool> {
+                        for absent_tag in self.absent_tags.iter_mut() {
+                            match absent_tag {
+                                [<$schema_name:camel AbsentTag>]::Probe(tag) => {
+                                    let key = &(tag.clone(), object.clone());
+                                    if self.database_transaction
+                                        .$schema_name
+                                        .tag_and_object
+                                        .exists(key).with_context(|| format!("Can not verify if key {key:?} exists in tag_and_object table"))?
+                                    {
+                                        return Ok(true);
+                                    }
+                                }
+                                [<$schema_name:camel AbsentTag>]::Merge { tag, cursor } => {
+                                    let target = (tag.clone(), object.clone());
+                                    cursor.advance_to(&target).with_context(|| format!("Can not propagate absent tag cursor to key {target:?}"))?;
+                                    if cursor.current_value.as_ref() == Some(&target) {
+                                        return Ok(true);
+                                    }
+                                }
+                            }
+                        }
+                        Ok(false)
+                    }
+                }
+
+                // cursor of one search term: entries of a tag in tag_and_object table or objects having attribute in range
+                enum [<$schema_name:camel TermCursor>]<'a> {
+                    Tag {
+                        tag: Id,
+                        cursor: [<$schema_name:camel Cursor>]<'a>,
+                    },
+                    Objects {
+                        objects: $crate::set_operations::Objects<'a>,
+                        current_object: Option<Id>,
+                        direction: Direction,
+                    },
+                }
+
+                impl<'a> [<$schema_name:camel TermCursor>]<'a> {
+                    // None once cursor went past objects of its term
+                    fn current_object(&self) -> Option<&Id> {
+                        match self {
+                            Self::Tag { tag, cursor } => cursor
+                                .current_value
+                                .as_ref()
+                                .filter(|(current_tag, _)| current_tag == tag)
+                                .map(|(_, object)| object),
+                            Self::Objects { current_object, .. } => current_object.as_ref(),
+                        }
+                    }
+
+                    fn next(&mut self) -> Result<()> {
+                        match self {
+                            Self::Tag { cursor, .. } => cursor.next(),
+                            Self::Objects { objects, current_object, .. } => {
+                                *current_object = objects.next().with_context(|| format!("Can not get next object after {current_object:?}"))?;
+                                Ok(())
+                            }
+                        }
+                    }
+
+                    // objects of attribute range are stepped over, as they are merged from cursors of distinct values
+                    fn advance_to(&mut self, object: &Id) -> Result<()> {
+                        match self {
+                            Self::Tag { tag, cursor } => cursor.advance_to(&(tag.clone(), object.clone())),
+                            Self::Objects { objects, current_object, direction } => {
+                                while current_object.as_ref().is_some_and(|current_object| direction.precedes(current_object, object)) {
+                                    *current_object = objects.next().with_context(|| format!("Can not get next object after {current_object:?}"))?;
+                                }
+                                Ok(())
+                            }
+                        }
+                    }
+                }
+
+                // tag cursors are created as search proceeds, while objects of attribute ranges are streamed since search creation
+                enum [<$schema_name:camel SearchTerm>]<'a> {
+                    Tag(Id),
+                    Objects(Option<$crate::set_operations::Objects<'a>>),
+                }
+
+                pub struct [<$schema_name:camel SearchIterator>]<'a> {
+                    database_transaction: &'a lawn_database::TablesTransactions,
+                    terms: Vec<[<$schema_name:camel SearchTerm>]<'a>>,
+                    absent_tags_filter: [<$schema_name:camel AbsentTagsFilter>]<'a>,
+                    start_after_object: Option<Id>,
+                    direction: Direction,
+                    cursors: Vec<[<$schema_name:camel TermCursor>]<'a>>,
+                    index_1: usize,
+                    index_2: usize,
+                    end: bool,
+                }
+
+                impl<'a> [<$schema_name:camel SearchIterator>]<'a> {
+                    // cursor of next term is positioned at the first of its objects not preceding given one
+                    fn push_cursor(&mut self, from_object: Id, skip_from_object: bool) -> Result<bool> {
+                        let term_index = self.cursors.len();
+                        let mut cursor = match &mut self.terms[term_index] {
+                            [<$schema_name:camel SearchTerm>]::Tag(tag) => [<$schema_name:camel TermCursor>]::Tag {
+                                tag: tag.clone(),
+                                cursor: [<$schema_name:camel Cursor>]::new(self.database_transaction, &(tag.clone(), from_object.clone()), self.direction)?,
+                            },
+                            [<$schema_name:camel SearchTerm>]::Objects(objects) => {
+                                let mut objects = objects.take().with_context(|| format!("Cursor of {}-th search term is already created", term_index + 1))?;
+                                let current_object = objects.next().with_context(|| "Can not get first object of attribute range")?;
+                                let mut cursor = [<$schema_name:camel TermCursor>]::Objects { objects, current_object, direction: self.direction };
+                                cursor.advance_to(&from_object)?;
+                                cursor
+                            }
+                        };
+                        if skip_from_object && cursor.current_object() == Some(&from_object) {
+                            cursor.next().with_context(|| format!("Can not propagate newely created cursor further (even getting nothing) to skip current entry as start_after_object {:?} is provided", self.start_after_object))?;
+                        }
+                        if cursor.current_object().is_none() {
+                            return Ok(false);
+                        }
+                        self.cursors.push(cursor);
+                        Ok(true)
+                    }
+
+                    fn cursor_object(&self, index: usize) -> Id {
+                        self.cursors[index].current_object().unwrap().clone()
+                    }
+                }
+
+                impl<'a> FallibleIterator for [<$schema_name:camel SearchIterator>]<'a> {
+                    type Item = Id;
+                    type Error = Error;
+
+                    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+                        if self.end {
+                            return Ok(None);
+                        }
+                        loop {
+                            if self.cursors.len() == self.terms.len() {
+                                let first_cursor_object = self.cursor_object(0);
+                                if self.cursors.iter().all(|cursor| cursor.current_object() == Some(&first_cursor_object)) {
+                                    let result = if self.absent_tags_filter.excludes(&first_cursor_object)? {
+                                        None
+                                    } else {
+                                        Some(first_cursor_object)
+                                    };
+                                    self.cursors[0].next().with_context(|| format!("Can not get next value for first cursor after object {result:?}"))?;
+                                    if self.cursors[0].current_object().is_none() {
+                                        self.end = true;
+                                    }
+                                    if result.is_some() || self.end {
+                                        return Ok(result);
+                                    }
+                                    continue;
+                                }
+                            }
+
+                            if self.cursors.len() < self.terms.len()
+                                && self.cursors.len() <= self.index_1
+                            {
+                                let (from_object, skip_from_object) = if self.index_1 == 0 {
+                                    (
+                                        self.start_after_object.clone().unwrap_or(self.direction.first_id()),
+                                        self.start_after_object.is_some(),
+                                    )
+                                } else {
+                                    (self.cursor_object(self.cursors.len() - 1), false)
+                                };
+                                if !self.push_cursor(from_object, skip_from_object)? {
+                                    self.end = true;
+                                    return Ok(None);
+                                }
+                            }
+
+                            if self.cursors.len() < self.terms.len()
+                                && self.cursors.len() <= self.index_2
+                                && !self.push_cursor(self.cursor_object(self.cursors.len() - 1), false)?
+                            {
+                                self.end = true;
+                                return Ok(None);
+                            }
+
+                            let target = self.cursor_object(self.index_1);
+                            self.cursors[self.index_2].advance_to(&target).with_context(|| format!("Can not propagate {:?}-th cursor further", self.index_2 + 1))?;
+                            let Some(index_2_object) = self.cursors[self.index_2].current_object().cloned() else {
+                                self.end = true;
+                                return Ok(None);
+                            };
+                            if index_2_object == target {
+                                self.index_1 = (self.index_1 + 1) % self.terms.len();
+                                self.index_2 = (self.index_2 + 1) % self.terms.len();
+                            } else {
+                                self.cursors[0].advance_to(&index_2_object).with_context(|| format!("Can not propagate first cursor further"))?;
+                                if self.cursors[0].current_object().is_none() {
+                                    self.end = true;
+                                    return Ok(None);
+                                }
+                                self.index_1 = 0;
+                                self.index_2 = 1;
+                            }
+                        }
+                    }
+                }
+            }
+        )+
+
+        impl Index {
+            pub fn new(config: IndexConfig) -> Result<Self> {
+                let mut result = Self {
+                    database: lawn_database::Database::new(config.database.clone()).with_context(|| format!("Can not create dream index using database config {:?}", config.database))?,
+                    config
+                };
+                result.migrate()?;
+                Ok(result)
+            }
+
+            // brings every schema to LAYOUT_VERSION and optional tables in line with config, done by new
+            pub fn migrate(&mut self) -> Result<()> {
+                self.lock_all_and_write(|transaction| {
+                    $(
+                        paste! {
+                            transaction.[<$schema_name _migrate>]()?;
+                        }
+                    )*
+                    Ok(())
+                }).with_context(|| "Can not migrate index layout")
+            }
+
+            pub fn lock_all_and_write<F, R>(&mut self, mut f: F) -> Result<R>
+            where
+                F: FnMut(&mut WriteTransaction<'_, '_>) -> Result<R>,
+            {
+                self.database
+                    .lock_all_and_write(|database_write_transaction| {
+                        f(&mut WriteTransaction {
+                            database_transaction: database_write_transaction,
+                            index_config: &self.config
+                        })
+                    }).with_context(|| "Can not lock lawn database and initiate write transaction")
+            }
+
+            pub fn lock_all_writes_and_read<F, R>(&self, mut f: F) -> Result<R>
+            where
+                F: FnMut(ReadTransaction) -> Result<R>,
+            {
+                self.database
+                    .lock_all_writes_and_read(|database_read_transaction| {
+                        f(ReadTransaction {
+                            database_transaction: database_read_transaction,
+                            index_config: &self.config
+                        })
+                    }).with_context(|| "Can not lock all write operations on lawn database and initiate read transaction")
+            }
+
+            fn object_tables_complete(&self) -> Result<bool> {
+                self.lock_all_writes_and_read(|transaction| {
+                    Ok(true $(&& paste! { transaction.[<$schema_name _layout>]()? }
+                        .is_some_and(|layout| layout.object_tables == $crate::ObjectTables::Complete))*)
+                })
+            }
+
+            // each batch is written in its own transaction, so migration can be interrupted and resumed using returned key;
+            // object_and_tag and object tables are maintained from the first batch on, yet read from only after the last batch of every schema,
+            // state of them is stored in schema layout, so index can be reopened maintaining them only after rebuild completes
+            $(
+                paste! {
+                    pub fn [<$schema_name _rebuild_object_tables_batch>](
+                        &mut self,
+                        start_after: Option<(Id, Id)>,
+                        batch_size: usize,
+                    ) -> Result<Option<(Id, Id)>> {
+                        let result = self.lock_all_and_write(|transaction| {
+                            transaction.[<$schema_name _rebuild_object_tables_batch>](start_after.clone(), batch_size)
+                        }).with_context(|| format!("Can not rebuild object tables batch of size {batch_size} starting after key {start_after:?}"))?;
+                        if result.is_none() && self.object_tables_complete()? {
+                            self.config.maintain_only_tag_and_object_table = false;
+                        }
+                        Ok(result)
+                    }
+
+                    pub fn [<$schema_name _drop_object_tables_batch>](&mut self, batch_size: usize) -> Result<bool> {
+                        self.config.maintain_only_tag_and_object_table = true;
+                        self.lock_all_and_write(|transaction| {
+                            transaction.[<$schema_name _drop_object_tables_batch>](batch_size)
+                        }).with_context(|| format!("Can not drop object tables batch of size {batch_size}"))
+                    }
+                }
+            )*
+
+            pub fn verify(&self) -> Result<VerificationReport> {
+                self.lock_all_writes_and_read(|transaction| {
+                    Ok(VerificationReport {
+                        $(
+                            $schema_name: paste! { transaction.[<$schema_name _verify>]()? },
+                        )*
+                    })
+                }).with_context(|| "Can not verify index")
+            }
+
+            // returns inconsistencies found and repaired
+            pub fn verify_and_repair(&mut self) -> Result<VerificationReport> {
+                self.lock_all_and_write(|transaction| {
+                    Ok(VerificationReport {
+                        $(
+                            $schema_name: paste! { transaction.[<$schema_name _repair>]()? },
+                        )*
+                    })
+                }).with_context(|| "Can not verify and repair index")
+            }
+
+            // schemas with object tables not complete have them dropped first, so no stale rows remain
+            pub fn rebuild_object_tables(&mut self, batch_size: usize) -> Result<()> {
+                $(
+                    paste! {
+                        let complete = self.lock_all_writes_and_read(|transaction| {
+                            Ok(transaction
+                                .[<$schema_name _layout>]()?
+                                .is_some_and(|layout| layout.object_tables == $crate::ObjectTables::Complete))
+                        })?;
+                        if !complete {
+                            while !self.[<$schema_name _drop_object_tables_batch>](batch_size)? {}
+                            let mut start_after = None;
+                            loop {
+                                start_after = self.[<$schema_name _rebuild_object_tables_batch>](start_after, batch_size)?;
+                                if start_after.is_none() {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                )*
+                Ok(())
+            }
+
+            pub fn drop_object_tables(&mut self, batch_size: usize) -> Result<()> {
+                $(
+                    paste! {
+                        while !self.[<$schema_name _drop_object_tables_batch>](batch_size)? {}
+                    }
+                )*
+                Ok(())
+            }
+        }
+        }
+    };
+}
+
+define_index!(test_index(
+    public
+) {
+} use {
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::collections::{BTreeMap, BTreeSet};
+
+    use fallible_iterator::FallibleIterator;
+    use nanorand::{Rng, WyRand};
+    use pretty_assertions::assert_eq;
+
+    fn new_index(
+        test_name_for_isolation: &str,
+        configure: impl FnOnce(&mut test_index::IndexConfig),
+    ) -> test_index::Index {
+        let mut config: test_index::IndexConfig = serde_saphyr::from_str(
+            &std::fs::read_to_string("src/test_index_config.yml")
+                .unwrap()
+                .replace("TEST_NAME", test_name_for_isolation),
+        )
+        .unwrap();
+        configure(&mut config);
+        test_index::Index::new(config).unwrap()
+    }
+
+    fn new_default_index(test_name_for_isolation: &str) -> test_index::Index {
+        new_index(test_name_for_isolation, |_| {})
+    }
+
+    fn new_lean_index(test_name_for_isolation: &str) -> test_index::Index {
+        new_index(test_name_for_isolation, |config| {
+            config.maintain_only_tag_and_object_table = true;
+            config.maintain_tag_count_table = true;
+        })
+    }
+
+    #[test]
+    fn test_simple() {
+        let mut index = new_default_index("test_simple");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))
+                    .unwrap()
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])
+                    .unwrap()
+                    .public_insert(&o3, &[t1.clone(), t2.clone(), t3.clone()])
+                    .unwrap();
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            &[t1.clone(), t2.clone(), t3.clone()],
+                            &[],
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o3)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(&[t1.clone(), t2.clone()], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o2.clone(), o3.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t1), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone()]
+                );
+
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            std::slice::from_ref(&t1),
+                            std::slice::from_ref(&t1),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            std::slice::from_ref(&t1),
+                            &[],
+                            Some(o3.clone()),
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], Some(o3.clone()), Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            &[],
+                            &[t1.clone(), t2.clone(), t3.clone()],
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            std::slice::from_ref(&t1),
+                            std::slice::from_ref(&t2),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            std::slice::from_ref(&t1),
+                            std::slice::from_ref(&t3),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            &[t1.clone(), t2.clone()],
+                            std::slice::from_ref(&t3),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o2)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            &[t1.clone(), t2.clone()],
+                            &[],
+                            Some(o1.clone()),
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    [o2.clone(), o3.clone()]
+                );
+
+                transaction.public_remove_tags_from_object(&o3, &[t1.clone(), t3.clone()])?;
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t1), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t2), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o2.clone(), o3.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t3), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+
+                transaction.public_remove_object(&o2)?;
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t1), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t2), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o3)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t3), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_absent_tags() {
+        let mut index = new_default_index("test_search_absent_tags");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let a1 = Id([13; 16]);
+        let a2 = Id([14; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o2, &[t1.clone(), t2.clone(), a1.clone()])?
+                    .public_insert(&o3, &[t1.clone(), t2.clone(), a2.clone()])?
+                    .public_insert(&o4, &[t1.clone(), t2.clone(), a1.clone(), a2.clone()])?;
+                // objects having any of absent tags are excluded, not only ones having all of them
+                for present_tags in [vec![t1.clone()], vec![t1.clone(), t2.clone()]] {
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                &present_tags,
+                                &[a1.clone(), a2.clone()],
+                                None,
+                                Direction::Ascending
+                            )?
+                            .collect::<Vec<_>>()?,
+                        vec![o1.clone()]
+                    );
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                &present_tags,
+                                std::slice::from_ref(&a1),
+                                None,
+                                Direction::Ascending
+                            )?
+                            .collect::<Vec<_>>()?,
+                        [o1.clone(), o3.clone()]
+                    );
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_union() {
+        let mut index = new_default_index("test_search_union");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o3, std::slice::from_ref(&t2))?
+                    .public_insert(&o4, &[t2.clone(), t3.clone()])?;
+                assert_eq!(
+                    transaction
+                        .public_search_union(
+                            &[t1.clone(), t2.clone()],
+                            &[],
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone(), o4.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search_union(
+                            &[t2.clone(), t1.clone(), t2.clone()],
+                            &[],
+                            Some(o2.clone()),
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    [o3.clone(), o4.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search_union(
+                            &[t1.clone(), t3.clone()],
+                            std::slice::from_ref(&t2),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search_union(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    []
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_count() {
+        let mut index = new_default_index("test_count");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let objects = (0..10u8).map(|i| Id([i; 16])).collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for (i, object) in objects.iter().enumerate() {
+                    if i % 2 == 0 {
+                        transaction.public_insert(object, &[t1.clone(), t2.clone()])?;
+                    } else {
+                        transaction.public_insert(object, std::slice::from_ref(&t1))?;
+                    }
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_count(&[], &[])?, 10);
+                assert_eq!(
+                    transaction.public_count(std::slice::from_ref(&t1), &[])?,
+                    10
+                );
+                assert_eq!(transaction.public_count(&[t1.clone(), t2.clone()], &[])?, 5);
+                assert_eq!(
+                    transaction
+                        .public_count(std::slice::from_ref(&t1), std::slice::from_ref(&t2))?,
+                    5
+                );
+                assert_eq!(
+                    transaction.public_count_with_limit(std::slice::from_ref(&t1), &[], 3)?,
+                    3
+                );
+                assert_eq!(
+                    transaction.public_count_with_limit(&[t1.clone(), t2.clone()], &[], 999)?,
+                    5
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_tag_cardinality() {
+        for maintain_tag_count_table in [false, true] {
+            let mut index = new_index(
+                &format!("test_tag_cardinality_{maintain_tag_count_table}"),
+                |config| config.maintain_tag_count_table = maintain_tag_count_table,
+            );
+
+            let t1 = Id([11; 16]);
+            let t2 = Id([12; 16]);
+            let t3 = Id([13; 16]);
+            let o1 = Id([21; 16]);
+            let o2 = Id([22; 16]);
+            let o3 = Id([23; 16]);
+
+            index
+                .lock_all_and_write(|transaction| {
+                    transaction
+                        .public_insert(&o1, &[t1.clone(), t1.clone()])?
+                        .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                        .public_insert(&o3, &[t1.clone(), t2.clone(), t3.clone()])?
+                        .public_insert(&o3, std::slice::from_ref(&t3))?;
+                    assert_eq!(transaction.public_tag_cardinality(&t1)?, 3);
+                    assert_eq!(transaction.public_tag_cardinality(&t2)?, 2);
+                    assert_eq!(transaction.public_tag_cardinality(&t3)?, 1);
+
+                    transaction.public_remove_tags_from_object(&o3, &[t2.clone(), t2.clone()])?;
+                    transaction.public_remove_object(&o2)?;
+                    transaction.public_remove_object(&o2)?;
+                    Ok(())
+                })
+                .unwrap();
+            index
+                .lock_all_writes_and_read(|transaction| {
+                    assert_eq!(transaction.public_tag_cardinality(&t1)?, 2);
+                    assert_eq!(transaction.public_tag_cardinality(&t2)?, 0);
+                    assert_eq!(transaction.public_tag_cardinality(&t3)?, 1);
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_layout_migration() {
+        let mut index = new_index("test_layout_migration", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+
+        // baseline layout: no layout table entry, nothing besides tag_and_object, object_and_tag and object tables
+        index
+            .database
+            .lock_all_and_write(|transaction| {
+                transaction.public.layout.remove(&());
+                for (tag, object) in [(&t1, &o1), (&t1, &o2), (&t2, &o2)] {
+                    transaction
+                        .public
+                        .tag_and_object
+                        .insert((tag.clone(), object.clone()), ());
+                    transaction
+                        .public
+                        .object_and_tag
+                        .insert((object.clone(), tag.clone()), ());
+                    transaction.public.object.insert(object.clone(), ());
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_layout()?, None);
+                Ok(())
+            })
+            .unwrap();
+
+        index.migrate().unwrap();
+        index
+            .lock_all_and_write(|transaction| {
+                assert_eq!(
+                    transaction.public_layout()?,
+                    Some(SchemaLayout {
+                        version: LAYOUT_VERSION,
+                        maintains_tag_count_table: true,
+                        object_tables: ObjectTables::Complete
+                    })
+                );
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 2);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 1);
+                transaction.public_remove_object(&o1)?;
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 1);
+                Ok(())
+            })
+            .unwrap();
+
+        index.config.maintain_tag_count_table = false;
+        index.migrate().unwrap();
+        index
+            .lock_all_and_write(|transaction| {
+                assert_eq!(
+                    transaction
+                        .database_transaction
+                        .public
+                        .tag_count
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .count()?,
+                    0
+                );
+                transaction.public_insert(&o1, &[t1.clone(), t2.clone()])?;
+                Ok(())
+            })
+            .unwrap();
+
+        index.config.maintain_tag_count_table = true;
+        index.migrate().unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 2);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 2);
+                Ok(())
+            })
+            .unwrap();
+
+        index
+            .database
+            .lock_all_and_write(|transaction| {
+                transaction.public.layout.insert(
+                    (),
+                    SchemaLayout {
+                        version: LAYOUT_VERSION + 1,
+                        maintains_tag_count_table: true,
+                        object_tables: ObjectTables::Complete,
+                    },
+                );
+                Ok(())
+            })
+            .unwrap();
+        assert!(index.migrate().is_err());
+    }
+
+    #[test]
+    fn test_three_tables_config() {
+        let mut config: test_index::IndexConfig = serde_saphyr::from_str(
+            &std::fs::read_to_string("src/test_three_tables_index_config.yml")
+                .unwrap()
+                .replace("TEST_NAME", "test_three_tables_config"),
+        )
+        .unwrap();
+        config.maintain_tag_count_table = true;
+        let mut index = test_index::Index::new(config).unwrap();
+
+        let t1 = Id([11; 16]);
+        let o1 = Id([21; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction.public_insert(&o1, std::slice::from_ref(&t1))?;
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 1);
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_selectivity() {
+        for maintain_tag_count_table in [false, true] {
+            let mut index = new_index(
+                &format!("test_search_selectivity_{maintain_tag_count_table}"),
+                |config| config.maintain_tag_count_table = maintain_tag_count_table,
+            );
+
+            let dense = Id([11; 16]);
+            let sparse = Id([12; 16]);
+            let absent = Id([13; 16]);
+            let objects = (0..2000u16)
+                .map(|i| {
+                    let mut object = [0u8; 16];
+                    object[..2].copy_from_slice(&i.to_be_bytes());
+                    Id(object)
+                })
+                .collect::<Vec<_>>();
+
+            index
+                .lock_all_and_write(|transaction| {
+                    for (i, object) in objects.iter().enumerate() {
+                        let mut tags = vec![dense.clone()];
+                        if i % 400 == 7 {
+                            tags.push(sparse.clone());
+                        }
+                        if i == 807 {
+                            tags.push(absent.clone());
+                        }
+                        transaction.public_insert(object, &tags)?;
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            index
+                .lock_all_writes_and_read(|transaction| {
+                    for present_tags in [
+                        [dense.clone(), sparse.clone()],
+                        [sparse.clone(), dense.clone()],
+                    ] {
+                        assert_eq!(
+                            transaction
+                                .public_search(&present_tags, &[], None, Direction::Ascending)?
+                                .collect::<Vec<_>>()?,
+                            [7, 407, 807, 1207, 1607].map(|i| objects[i].clone())
+                        );
+                        assert_eq!(
+                            transaction
+                                .public_search(
+                                    &present_tags,
+                                    std::slice::from_ref(&absent),
+                                    Some(objects[407].clone()),
+                                    Direction::Ascending
+                                )?
+                                .collect::<Vec<_>>()?,
+                            [1207, 1607].map(|i| objects[i].clone())
+                        );
+                        // start_after_object lacking the rarest tag must not make its cursor skip the next match
+                        assert_eq!(
+                            transaction
+                                .public_search(
+                                    &present_tags,
+                                    &[],
+                                    Some(objects[400].clone()),
+                                    Direction::Ascending
+                                )?
+                                .collect::<Vec<_>>()?,
+                            [407, 807, 1207, 1607].map(|i| objects[i].clone())
+                        );
+                    }
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_search_estimation_budget() {
+        let mut index = new_default_index("test_search_estimation_budget");
+
+        // estimating cardinalities of all the dense tags would read more rows than estimation budget allows
+        let dense_tags = (0..8u8).map(|i| Id([i; 16])).collect::<Vec<_>>();
+        let sparse = Id([21; 16]);
+        let absent = Id([22; 16]);
+        let objects = (0..1500u16)
+            .map(|i| {
+                let mut object = [0u8; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for (i, object) in objects.iter().enumerate() {
+                    let mut tags = dense_tags.clone();
+                    if i % 300 == 5 {
+                        tags.push(sparse.clone());
+                    }
+                    if i == 605 {
+                        tags.push(absent.clone());
+                    }
+                    transaction.public_insert(object, &tags)?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                let mut present_tags = dense_tags.clone();
+                present_tags.push(sparse.clone());
+                for direction in [Direction::Ascending, Direction::Descending] {
+                    let mut expected = [5, 305, 905, 1205].map(|i| objects[i].clone()).to_vec();
+                    if direction.is_descending() {
+                        expected.reverse();
+                    }
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                &present_tags,
+                                std::slice::from_ref(&absent),
+                                None,
+                                direction
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected
+                    );
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_absent_tags_strategies() {
+        for maintain_tag_count_table in [false, true] {
+            let mut index = new_index(
+                &format!("test_search_absent_tags_strategies_{maintain_tag_count_table}"),
+                |config| config.maintain_tag_count_table = maintain_tag_count_table,
+            );
+
+            let all = Id([11; 16]);
+            let half = Id([12; 16]);
+            let dense_absent = Id([13; 16]);
+            let sparse_absent = Id([14; 16]);
+            let objects = (0..3000u16)
+                .map(|i| {
+                    let mut object = [0u8; 16];
+                    object[..2].copy_from_slice(&i.to_be_bytes());
+                    Id(object)
+                })
+                .collect::<Vec<_>>();
+
+            index
+                .lock_all_and_write(|transaction| {
+                    for (i, object) in objects.iter().enumerate() {
+                        let mut tags = vec![all.clone()];
+                        if i % 2 == 0 {
+                            tags.push(half.clone());
+                        }
+                        if i % 3 != 0 {
+                            tags.push(dense_absent.clone());
+                        }
+                        if i % 1000 == 6 {
+                            tags.push(sparse_absent.clone());
+                        }
+                        transaction.public_insert(object, &tags)?;
+                    }
+                    Ok(())
+                })
+                .unwrap();
+            index
+                .lock_all_writes_and_read(|transaction| {
+                    let absent_tags = [dense_absent.clone(), sparse_absent.clone()];
+                    let expected = |step: usize| {
+                        (0..objects.len())
+                            .filter(|i| i % step == 0 && i % 1000 != 6)
+                            .map(|i| objects[i].clone())
+                            .collect::<Vec<_>>()
+                    };
+                    assert_eq!(
+                        transaction
+                            .public_search(&[], &absent_tags, None, Direction::Ascending)?
+                            .collect::<Vec<_>>()?,
+                        expected(3)
+                    );
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                std::slice::from_ref(&all),
+                                &absent_tags,
+                                None,
+                                Direction::Ascending
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected(3)
+                    );
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                &[all.clone(), half.clone()],
+                                &absent_tags,
+                                None,
+                                Direction::Ascending
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected(6)
+                    );
+                    assert_eq!(
+                        transaction
+                            .public_search(
+                                &[all.clone(), half.clone()],
+                                &absent_tags,
+                                Some(objects[1500].clone()),
+                                Direction::Ascending
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected(6)
+                            .into_iter()
+                            .filter(|object| *object > objects[1500])
+                            .collect::<Vec<_>>()
+                    );
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_search_descending() {
+        let mut index = new_default_index("test_search_descending");
+        let mut rng = WyRand::new_seed(0);
+
+        let tags = (0..6u8).map(|i| Id([i; 16])).collect::<Vec<_>>();
+        index
+            .lock_all_and_write(|transaction| {
+                for _ in 0..500 {
+                    let mut object = [0u8; 16];
+                    rng.fill(&mut object);
+                    let object_tags = tags
+                        .iter()
+                        .filter(|_| rng.generate_range(0..2u8) == 0)
+                        .cloned()
+                        .collect::<Vec<_>>();
+                    transaction.public_insert(&Id(object), &object_tags)?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                for (present_tags, absent_tags) in [
+                    (vec![], vec![]),
+                    (vec![], vec![tags[0].clone()]),
+                    (vec![tags[1].clone()], vec![]),
+                    (vec![tags[1].clone()], vec![tags[2].clone()]),
+                    (vec![tags[1].clone(), tags[3].clone()], vec![]),
+                    (
+                        vec![tags[1].clone(), tags[3].clone(), tags[4].clone()],
+                        vec![tags[5].clone()],
+                    ),
+                ] {
+                    let ascending = transaction
+                        .public_search(&present_tags, &absent_tags, None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?;
+                    let mut descending = transaction
+                        .public_search(&present_tags, &absent_tags, None, Direction::Descending)?
+                        .collect::<Vec<_>>()?;
+                    descending.reverse();
+                    assert_eq!(descending, ascending);
+
+                    let start_before_object = ascending[ascending.len() / 2].clone();
+                    let mut descending_page = transaction
+                        .public_search(
+                            &present_tags,
+                            &absent_tags,
+                            Some(start_before_object.clone()),
+                            Direction::Descending,
+                        )?
+                        .collect::<Vec<_>>()?;
+                    descending_page.reverse();
+                    assert_eq!(descending_page, ascending[..ascending.len() / 2]);
+                }
+
+                let query = (Query::Tag(tags[0].clone()) & !Query::Tag(tags[1].clone()))
+                    | Query::Tag(tags[2].clone());
+                let ascending = transaction
+                    .public_query(&query, None, Direction::Ascending)?
+                    .collect::<Vec<_>>()?;
+                let mut descending = transaction
+                    .public_query(&query, None, Direction::Descending)?
+                    .collect::<Vec<_>>()?;
+                descending.reverse();
+                assert_eq!(descending, ascending);
+
+                let ascending = transaction
+                    .public_search_union(&tags[..2], &[], None, Direction::Ascending)?
+                    .collect::<Vec<_>>()?;
+                let mut descending = transaction
+                    .public_search_union(&tags[..2], &[], None, Direction::Descending)?
+                    .collect::<Vec<_>>()?;
+                descending.reverse();
+                assert_eq!(descending, ascending);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_search_cursor_token() {
+        let mut index = new_default_index("test_search_cursor_token");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let objects = (0..10u8).map(|i| Id([i; 16])).collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for (i, object) in objects.iter().enumerate() {
+                    if i == 4 {
+                        transaction.public_insert(object, &[t1.clone(), t2.clone()])?;
+                    } else {
+                        transaction.public_insert(object, std::slice::from_ref(&t1))?;
+                    }
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                for direction in [Direction::Ascending, Direction::Descending] {
+                    let mut result = Vec::new();
+                    let mut token = Some(transaction.public_search_token(
+                        std::slice::from_ref(&t1),
+                        std::slice::from_ref(&t2),
+                        direction,
+                    ));
+                    while let Some(current_token) = token {
+                        let parsed_token = current_token
+                            .to_string()
+                            .parse::<SearchCursorToken>()
+                            .unwrap();
+                        assert_eq!(parsed_token, current_token);
+                        let (page, next_token) =
+                            transaction.public_search_page(&parsed_token, 3)?;
+                        assert!(page.len() <= 3);
+                        result.extend(page);
+                        token = next_token;
+                    }
+                    if direction == Direction::Descending {
+                        result.reverse();
+                    }
+                    assert_eq!(
+                        result,
+                        objects
+                            .iter()
+                            .enumerate()
+                            .filter(|(i, _)| *i != 4)
+                            .map(|(_, object)| object.clone())
+                            .collect::<Vec<_>>()
+                    );
+                }
+
+                let token = transaction.public_search_token(
+                    std::slice::from_ref(&t1),
+                    &[],
+                    Direction::Ascending,
+                );
+                assert_eq!(
+                    transaction.public_search_page(&token, 0)?,
+                    (Vec::new(), Some(token.clone()))
+                );
+                assert_eq!(
+                    transaction.public_search_page(&token, usize::MAX)?.0.len(),
+                    objects.len()
+                );
+
+                let text = transaction
+                    .public_search_token(std::slice::from_ref(&t1), &[], Direction::Ascending)
+                    .resumed_after(objects[2].clone())
+                    .to_string();
+                let mut corrupted = text.clone().into_bytes();
+                corrupted[3] = if corrupted[3] == b'A' { b'B' } else { b'A' };
+                assert_eq!(
+                    String::from_utf8(corrupted)
+                        .unwrap()
+                        .parse::<SearchCursorToken>(),
+                    Err(SearchCursorTokenError::ChecksumMismatch)
+                );
+                assert_eq!(
+                    text[..4].parse::<SearchCursorToken>(),
+                    Err(SearchCursorTokenError::Truncated)
+                );
+                assert_eq!(
+                    format!("{}.", &text[..8]).parse::<SearchCursorToken>(),
+                    Err(SearchCursorTokenError::InvalidCharacter { position: 8 })
+                );
+
+                let foreign_token = SearchCursorToken::new(
+                    "private",
+                    std::slice::from_ref(&t1),
+                    &[],
+                    Direction::Ascending,
+                );
+                assert_eq!(
+                    transaction
+                        .public_search_by_token(&foreign_token)
+                        .err()
+                        .unwrap()
+                        .downcast::<SearchCursorTokenError>()
+                        .unwrap(),
+                    SearchCursorTokenError::WrongSchema {
+                        expected: "public".to_string(),
+                        found: "private".to_string()
+                    }
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_insert_many() {
+        let mut index = new_index("test_insert_many", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction.public_insert(&o1, std::slice::from_ref(&t1))?;
+                assert_eq!(
+                    transaction.public_insert_many([
+                        (o3.clone(), vec![t2.clone(), t1.clone(), t2.clone()]),
+                        (o1.clone(), vec![t1.clone(), t3.clone()]),
+                        (o2.clone(), vec![t1.clone()]),
+                        (o3.clone(), vec![t3.clone()]),
+                        (o4.clone(), vec![]),
+                    ])?,
+                    5
+                );
+                assert_eq!(
+                    transaction.public_insert_many([(o2.clone(), vec![t1.clone()])])?,
+                    0
+                );
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t1), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone()]
+                );
+                assert_eq!(transaction.public_get_tags(&o4)?, []);
+                assert_eq!(
+                    transaction.public_get_tags(&o3)?,
+                    [t1.clone(), t2.clone(), t3.clone()]
+                );
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 3);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 1);
+                assert_eq!(transaction.public_tag_cardinality(&t3)?, 2);
+                Ok(())
+            })
+            .unwrap();
+        assert!(index.verify().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_set_tags() {
+        let mut index = new_index("test_set_tags", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o2, std::slice::from_ref(&t2))?;
+                assert_eq!(
+                    transaction.public_set_tags(&o1, &[t3.clone(), t2.clone(), t3.clone()])?,
+                    TagsDiff {
+                        added: vec![t3.clone()],
+                        removed: vec![t1.clone()]
+                    }
+                );
+                assert_eq!(transaction.public_get_tags(&o1)?, [t2.clone(), t3.clone()]);
+                assert_eq!(
+                    transaction.public_set_tags(&o1, &[t2.clone(), t3.clone()])?,
+                    TagsDiff::default()
+                );
+                assert_eq!(
+                    transaction.public_set_tags(&o2, &[])?,
+                    TagsDiff {
+                        added: vec![],
+                        removed: vec![t2.clone()]
+                    }
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 0);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 1);
+                assert_eq!(transaction.public_tag_cardinality(&t3)?, 1);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_tag() {
+        let mut index = new_index("test_remove_tag", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o3, std::slice::from_ref(&t2))?;
+                assert_eq!(transaction.public_remove_tag(&t1)?, 2);
+                assert_eq!(transaction.public_remove_tag(&t1)?, 0);
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_has_object_with_tag(&t1)?, false);
+                assert_eq!(transaction.public_has_tag(&o2, &t1)?, false);
+                assert_eq!(transaction.public_get_tags(&o1)?, []);
+                assert_eq!(transaction.public_get_tags(&o2)?, std::slice::from_ref(&t2));
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o2.clone(), o3.clone()]
+                );
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 0);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 2);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_remove_tag_in_chunks() {
+        let mut index = new_index("test_remove_tag_in_chunks", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let objects = (0..2049u16)
+            .map(|i| {
+                let mut object = [0x21; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for object in objects.iter() {
+                    transaction.public_insert(object, std::slice::from_ref(&t1))?;
+                }
+                transaction.public_insert(&objects[0], std::slice::from_ref(&t2))?;
+                assert_eq!(transaction.public_remove_tag(&t1)?, objects.len());
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_has_object_with_tag(&t1)?, false);
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 0);
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&objects[0])
+                );
+                Ok(())
+            })
+            .unwrap();
+        assert!(index.verify().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_remove_tag_names() {
+        for maintain_tag_name_table in [false, true] {
+            let mut index = new_index(
+                &format!("test_remove_tag_names_{maintain_tag_name_table}"),
+                |config| {
+                    config.maintain_tag_dictionary_table = true;
+                    config.maintain_tag_name_table = maintain_tag_name_table;
+                },
+            );
+
+            let red = Id([11; 16]);
+            let big = Id([12; 16]);
+            let o1 = Id([21; 16]);
+
+            index
+                .lock_all_and_write(|transaction| {
+                    transaction
+                        .public_insert_named(&o1, &[("red", red.clone()), ("big", big.clone())])?
+                        .public_register_tag_names(&[("crimson", red.clone())])?
+                        .public_remove_tag(&red)?;
+                    let dictionary = transaction
+                        .database_transaction
+                        .public
+                        .tag_dictionary
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .map(|(key, _)| Ok(key))
+                        .collect::<Vec<_>>()?;
+                    assert_eq!(dictionary, [(b"big".to_vec(), big.clone())]);
+                    Ok(())
+                })
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_merge_tags() {
+        let mut index = new_index("test_merge_tags", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o3, &[t2.clone(), t3.clone()])?;
+                assert_eq!(transaction.public_merge_tags(&t1, &t2)?, 2);
+                assert_eq!(transaction.public_merge_tags(&t1, &t2)?, 0);
+                assert_eq!(transaction.public_merge_tags(&t3, &t3)?, 0);
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_has_object_with_tag(&t1)?, false);
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t2), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone()]
+                );
+                assert_eq!(transaction.public_get_tags(&o1)?, std::slice::from_ref(&t2));
+                assert_eq!(transaction.public_get_tags(&o2)?, std::slice::from_ref(&t2));
+                assert_eq!(transaction.public_get_tags(&o3)?, [t2.clone(), t3.clone()]);
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 0);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 3);
+                assert_eq!(transaction.public_tag_cardinality(&t3)?, 1);
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_merge_tags_in_chunks() {
+        let mut index = new_index("test_merge_tags_in_chunks", |config| {
+            config.maintain_tag_count_table = true
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let objects = (0..2049u16)
+            .map(|i| {
+                let mut object = [0x21; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for object in objects.iter() {
+                    transaction.public_insert(object, std::slice::from_ref(&t1))?;
+                }
+                transaction.public_insert(&objects[0], std::slice::from_ref(&t2))?;
+                assert_eq!(transaction.public_merge_tags(&t1, &t2)?, objects.len());
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_has_object_with_tag(&t1)?, false);
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 0);
+                assert_eq!(
+                    transaction.public_tag_cardinality(&t2)?,
+                    objects.len() as u64
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t2), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    objects
+                );
+                Ok(())
+            })
+            .unwrap();
+        assert!(index.verify().unwrap().is_consistent());
+    }
+
+    #[test]
+    fn test_merge_tags_names() {
+        for maintain_tag_name_table in [false, true] {
+            let mut index = new_index(
+                &format!("test_merge_tags_names_{maintain_tag_name_table}"),
+                |config| {
+                    config.maintain_tag_dictionary_table = true;
+                    config.maintain_tag_name_table = maintain_tag_name_table;
+                },
+            );
+
+            let red = Id([11; 16]);
+            let scarlet = Id([12; 16]);
+            let o1 = Id([21; 16]);
+            let o2 = Id([22; 16]);
+
+            index
+                .lock_all_and_write(|transaction| {
+                    transaction
+                        .public_insert_named(&o1, &[("red", red.clone())])?
+                        .public_insert_named(&o2, &[("scarlet", scarlet.clone())])?
+                        .public_register_tag_names(&[("crimson", red.clone())])?;
+                    assert_eq!(transaction.public_merge_tags(&red, &scarlet)?, 1);
+                    let dictionary = transaction
+                        .database_transaction
+                        .public
+                        .tag_dictionary
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .map(|(key, _)| Ok(key))
+                        .collect::<Vec<_>>()?;
+                    assert_eq!(dictionary, [(b"scarlet".to_vec(), scarlet.clone())]);
+                    let names = transaction
+                        .database_transaction
+                        .public
+                        .tag_name
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .collect::<Vec<_>>()?;
+                    if maintain_tag_name_table {
+                        assert_eq!(names, [(scarlet.clone(), b"scarlet".to_vec())]);
+                    } else {
+                        assert_eq!(names, []);
+                    }
+                    Ok(())
+                })
+                .unwrap();
         }
+    }
+
+    #[test]
+    fn test_lean_mode() {
+        let mut index = new_index("test_lean_mode", |config| {
+            config.maintain_only_tag_and_object_table = true;
+            config.maintain_tag_count_table = true;
+            config.maintain_attribute_tables = true;
+        });
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let t3 = Id([13; 16]);
+        let t4 = Id([14; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+        let a1 = Id([31; 16]);
+
+        let is_lean_mode_error =
+            |error: anyhow::Error| error.downcast_ref::<LeanModeError>().is_some();
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                    .public_set_attribute(&o3, &a1, 5u64)?;
+                assert_eq!(
+                    transaction.public_insert_many([
+                        (o3.clone(), vec![t1.clone(), t2.clone(), t3.clone()]),
+                        (o4.clone(), vec![t3.clone()]),
+                    ])?,
+                    4
+                );
+
+                assert_eq!(transaction.public_has_tag(&o2, &t2)?, true);
+                assert_eq!(transaction.public_has_tag(&o1, &t2)?, false);
+                assert_eq!(transaction.public_has_object_with_tag(&t3)?, true);
+                assert_eq!(transaction.public_has_object_with_tag(&t4)?, false);
+                assert!(is_lean_mode_error(
+                    transaction.public_get_tags(&o3).unwrap_err()
+                ));
+                assert!(is_lean_mode_error(
+                    transaction.public_get_tag_names(&o3).unwrap_err()
+                ));
+                assert_eq!(transaction.public_tag_cardinality(&t1)?, 3);
+                Ok(())
+            })
+            .unwrap();
+
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t1), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone(), o3.clone()]
+                );
+                assert_eq!(
+                    transaction
+                        .public_search(
+                            &[t1.clone(), t2.clone()],
+                            std::slice::from_ref(&t3),
+                            None,
+                            Direction::Descending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o2)
+                );
+                assert!(is_lean_mode_error(
+                    transaction
+                        .public_search(&[], std::slice::from_ref(&t1), None, Direction::Ascending)
+                        .err()
+                        .unwrap()
+                ));
+                assert_eq!(transaction.public_count(std::slice::from_ref(&t3), &[])?, 2);
+                assert!(is_lean_mode_error(
+                    transaction.public_count(&[], &[]).unwrap_err()
+                ));
+                assert_eq!(
+                    transaction.public_count_with_limit(std::slice::from_ref(&t1), &[], 2)?,
+                    2
+                );
+                let (page, next_token) = transaction.public_search_page(
+                    &transaction.public_search_token(
+                        std::slice::from_ref(&t1),
+                        &[],
+                        Direction::Ascending,
+                    ),
+                    2,
+                )?;
+                assert_eq!(page, [o1.clone(), o2.clone()]);
+                assert_eq!(
+                    transaction
+                        .public_search_by_token(&next_token.unwrap())?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o3)
+                );
+                assert_eq!(
+                    transaction
+                        .public_search_union(
+                            &[t2.clone(), t3.clone()],
+                            std::slice::from_ref(&t1),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o4)
+                );
+                assert_eq!(
+                    transaction
+                        .public_query(
+                            &(Query::Tag(t1.clone())
+                                & !(Query::Tag(t2.clone()) | Query::Tag(t3.clone()))),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert_eq!(
+                    transaction
+                        .public_query(
+                            &Query::And(vec![
+                                Query::Not(Box::new(Query::Or(vec![
+                                    Query::Tag(t2.clone()),
+                                    Query::Tag(t3.clone())
+                                ]))),
+                                Query::And(vec![Query::Tag(t1.clone())]),
+                            ]),
+                            None,
+                            Direction::Ascending
+                        )?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o1)
+                );
+                assert!(is_lean_mode_error(
+                    transaction
+                        .public_query(&!Query::Tag(t1.clone()), None, Direction::Ascending)
+                        .err()
+                        .unwrap()
+                ));
+                assert!(is_lean_mode_error(
+                    transaction
+                        .public_query(
+                            &!(Query::Tag(t2.clone()) | Query::Tag(t3.clone())),
+                            None,
+                            Direction::Ascending
+                        )
+                        .err()
+                        .unwrap()
+                ));
+                Ok(())
+            })
+            .unwrap();
+
+        index
+            .lock_all_and_write(|transaction| {
+                assert!(is_lean_mode_error(
+                    transaction
+                        .public_set_tags(&o1, &[t2.clone(), t4.clone()])
+                        .unwrap_err()
+                ));
+                assert!(
+                    transaction
+                        .public_remove_object(&o3)
+                        .is_err_and(is_lean_mode_error)
+                );
+                assert_eq!(transaction.public_get_attribute::<u64>(&o3, &a1)?, Some(5));
+                transaction
+                    .public_remove_tags_from_object(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o1, &[t2.clone(), t4.clone()])?
+                    .public_remove_tags_from_object(&o2, std::slice::from_ref(&t1))?
+                    .public_remove_tags_from_object(&o3, &[t1.clone(), t2.clone(), t3.clone()])?;
+                let object_tags = |transaction: &test_index::WriteTransaction<'_, '_>,
+                                   object: &Id|
+                 -> anyhow::Result<Vec<Id>> {
+                    let mut result = Vec::new();
+                    for tag in [&t1, &t2, &t3, &t4] {
+                        if transaction.public_has_tag(object, tag)? {
+                            result.push(tag.clone());
+                        }
+                    }
+                    Ok(result)
+                };
+                assert_eq!(object_tags(transaction, &o1)?, [t2.clone(), t4.clone()]);
+                assert_eq!(object_tags(transaction, &o2)?, std::slice::from_ref(&t2));
+                assert_eq!(object_tags(transaction, &o3)?, []);
+                assert_eq!(transaction.public_has_object_with_tag(&t1)?, false);
+                assert_eq!(transaction.public_tag_cardinality(&t2)?, 2);
+                assert_eq!(transaction.public_tag_cardinality(&t3)?, 1);
+
+                assert_eq!(transaction.public_merge_tags(&t4, &t3)?, 1);
+                assert_eq!(object_tags(transaction, &o1)?, [t2.clone(), t3.clone()]);
+                assert_eq!(transaction.public_tag_cardinality(&t3)?, 2);
+                assert_eq!(transaction.public_remove_tag(&t2)?, 2);
+                assert_eq!(object_tags(transaction, &o1)?, std::slice::from_ref(&t3));
+                assert_eq!(object_tags(transaction, &o2)?, []);
+                assert_eq!(
+                    transaction
+                        .public_search(std::slice::from_ref(&t3), &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o4.clone()]
+                );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_object_tables_migration() {
+        let mut index = new_lean_index("test_object_tables_migration");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([20; 16]);
+
+        let object_tables = |index: &test_index::Index| {
+            index
+                .lock_all_writes_and_read(|transaction| {
+                    Ok(transaction.public_layout()?.unwrap().object_tables)
+                })
+                .unwrap()
+        };
+        let all_objects = |index: &test_index::Index| {
+            index.lock_all_writes_and_read(|transaction| {
+                transaction
+                    .public_search(&[], &[], None, Direction::Ascending)?
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?
+                    .public_insert(&o3, std::slice::from_ref(&t2))?;
+                transaction
+                    .database_transaction
+                    .public
+                    .object
+                    .insert(o4.clone(), ());
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(object_tables(&index), ObjectTables::Absent);
+
+        assert!(index.public_rebuild_object_tables_batch(None, 0).is_err());
+        assert!(index.public_drop_object_tables_batch(0).is_err());
+        assert!(index.public_rebuild_object_tables_batch(None, 2).is_err());
+        assert!(
+            index
+                .public_rebuild_object_tables_batch(Some((t1.clone(), o2.clone())), 2)
+                .is_err()
+        );
+        assert_eq!(index.public_drop_object_tables_batch(2).unwrap(), true);
+
+        let start_after = index.public_rebuild_object_tables_batch(None, 2).unwrap();
+        assert_eq!(start_after, Some((t1.clone(), o2.clone())));
+        assert_eq!(object_tables(&index), ObjectTables::Building);
+        assert_eq!(index.config.maintain_only_tag_and_object_table, true);
+        assert!(
+            all_objects(&index)
+                .unwrap_err()
+                .downcast_ref::<LeanModeError>()
+                .is_some()
+        );
+        index
+            .lock_all_and_write(|transaction| {
+                transaction.public_insert(&o4, std::slice::from_ref(&t1))?;
+                Ok(())
+            })
+            .unwrap();
+        let start_after = index
+            .public_rebuild_object_tables_batch(start_after, 2)
+            .unwrap();
+        assert_eq!(start_after, Some((t2.clone(), o3.clone())));
+        assert_eq!(
+            index
+                .public_rebuild_object_tables_batch(start_after, 2)
+                .unwrap(),
+            None
+        );
+        assert_eq!(object_tables(&index), ObjectTables::Complete);
+        assert_eq!(index.config.maintain_only_tag_and_object_table, false);
+        assert_eq!(
+            all_objects(&index).unwrap(),
+            [o4.clone(), o1.clone(), o2.clone(), o3.clone()]
+        );
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(
+                    transaction
+                        .public_search(&[], std::slice::from_ref(&t1), None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    std::slice::from_ref(&o3)
+                );
+                assert_eq!(transaction.public_get_tags(&o4)?, std::slice::from_ref(&t1));
+                Ok(())
+            })
+            .unwrap();
+        index.migrate().unwrap();
+
+        index.drop_object_tables(2).unwrap();
+        assert_eq!(index.config.maintain_only_tag_and_object_table, true);
+        assert_eq!(object_tables(&index), ObjectTables::Absent);
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert!(
+                    transaction
+                        .database_transaction
+                        .public
+                        .object_and_tag
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .next()?
+                        .is_none()
+                );
+                assert!(
+                    transaction
+                        .database_transaction
+                        .public
+                        .object
+                        .iter(std::ops::Bound::Unbounded, false)?
+                        .next()?
+                        .is_none()
+                );
+                Ok(())
+            })
+            .unwrap();
+        index.config.maintain_only_tag_and_object_table = false;
+        assert!(index.migrate().is_err());
+        index.config.maintain_only_tag_and_object_table = true;
+
+        index
+            .database
+            .lock_all_and_write(|transaction| {
+                transaction.public.object.insert(Id([99; 16]), ());
+                Ok(())
+            })
+            .unwrap();
+        index.rebuild_object_tables(1).unwrap();
+        assert_eq!(index.config.maintain_only_tag_and_object_table, false);
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Descending)?
+                        .collect::<Vec<_>>()?,
+                    [o3.clone(), o2.clone(), o1.clone(), o4.clone()]
+                );
+                Ok(())
+            })
+            .unwrap();
+        index.rebuild_object_tables(1).unwrap();
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut index = new_default_index("test_verify");
+
+        let t1 = Id([11; 16]);
+        let t2 = Id([12; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&t1))?
+                    .public_insert(&o2, &[t1.clone(), t2.clone()])?;
+                Ok(())
+            })
+            .unwrap();
+        assert!(index.verify().unwrap().is_consistent());
+
+        index
+            .lock_all_and_write(|transaction| {
+                let tables = &mut transaction.database_transaction.public;
+                tables.tag_and_object.insert((t2.clone(), o1.clone()), ());
+                tables.object_and_tag.insert((o3.clone(), t1.clone()), ());
+                tables.object.insert(o4.clone(), ());
+                tables.object.remove(&o2);
+                Ok(())
+            })
+            .unwrap();
+        let report = index.verify().unwrap();
+        assert!(!report.is_consistent());
+        assert_eq!(
+            report.public,
+            SchemaVerificationReport {
+                pairs_only_in_tag_and_object: Finding {
+                    count: 1,
+                    samples: vec![(t2.clone(), o1.clone())]
+                },
+                pairs_only_in_object_and_tag: Finding {
+                    count: 1,
+                    samples: vec![(t1.clone(), o3.clone())]
+                },
+                untagged_objects: Finding {
+                    count: 1,
+                    samples: vec![o4.clone()]
+                },
+                missing_objects: Finding {
+                    count: 2,
+                    samples: vec![o2.clone(), o3.clone()]
+                },
+            }
+        );
+
+        let report = index.verify_and_repair().unwrap();
+        assert_eq!(
+            report.public.missing_objects.samples,
+            std::slice::from_ref(&o2)
+        );
+        assert!(index.verify().unwrap().is_consistent());
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(transaction.public_get_tags(&o1)?, [t1.clone(), t2.clone()]);
+                assert_eq!(transaction.public_get_tags(&o3)?, []);
+                assert_eq!(
+                    transaction
+                        .public_search(&[], &[], None, Direction::Ascending)?
+                        .collect::<Vec<_>>()?,
+                    [o1.clone(), o2.clone()]
+                );
+                Ok(())
+            })
+            .unwrap();
+
+        let stale_objects = (0..REPAIR_CHUNK_SIZE as u16 * 2 + 1)
+            .map(|i| {
+                let mut object = [0xff; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+        index
+            .lock_all_and_write(|transaction| {
+                for object in stale_objects.iter() {
+                    transaction
+                        .database_transaction
+                        .public
+                        .object
+                        .insert(object.clone(), ());
+                }
+                Ok(())
+            })
+            .unwrap();
+        let report = index.verify().unwrap();
+        assert_eq!(report.public.untagged_objects.count, stale_objects.len());
+        assert_eq!(
+            report.public.untagged_objects.samples,
+            stale_objects[..FINDING_SAMPLES_LIMIT]
+        );
+        assert_eq!(
+            index.verify_and_repair().unwrap().public.untagged_objects,
+            report.public.untagged_objects
+        );
+        assert!(index.verify().unwrap().is_consistent());
+
+        index.drop_object_tables(1024).unwrap();
+        for error in [
+            index.verify().unwrap_err(),
+            index.verify_and_repair().unwrap_err(),
+        ] {
+            assert!(error.downcast_ref::<LeanModeError>().is_some());
         }
-    };
-}
+    }
 
-define_index!(test_index(
-    public
-) {
-} use {
-});
+    #[test]
+    fn test_suggest() {
+        let mut index = new_index("test_suggest", |config| {
+            config.maintain_tag_count_table = true;
+            config.maintain_tag_dictionary_table = true;
+        });
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        let red = ("color:red", Id([11; 16]));
+        let green = ("color:green", Id([12; 16]));
+        let grey = ("color:grey", Id([13; 16]));
+        let big = ("size:big", Id([14; 16]));
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
 
-    use std::collections::{BTreeMap, BTreeSet};
+        let named = |(name, tag): &(&str, Id)| (name.as_bytes().to_vec(), tag.clone());
 
-    use fallible_iterator::FallibleIterator;
-    use nanorand::{Rng, WyRand};
-    use pretty_assertions::assert_eq;
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert_named(&o1, &[red.clone(), green.clone(), big.clone()])?
+                    .public_insert_named(&o2, &[grey.clone(), big.clone()])?
+                    .public_insert_named(&o3, std::slice::from_ref(&grey))?;
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                assert_eq!(
+                    transaction.public_suggest(b"color:", 10, false)?,
+                    [named(&green), named(&grey), named(&red)]
+                );
+                assert_eq!(
+                    transaction.public_suggest(b"color:gr", 1, false)?,
+                    [named(&green)]
+                );
+                assert_eq!(
+                    transaction.public_suggest(b"color:", 2, true)?,
+                    [named(&grey), named(&green)]
+                );
+                assert_eq!(
+                    transaction.public_suggest(b"", 10, true)?,
+                    [named(&grey), named(&big), named(&green), named(&red)]
+                );
+                assert_eq!(transaction.public_suggest(b"shape:", 10, false)?, []);
+                Ok(())
+            })
+            .unwrap();
 
-    fn new_default_index(test_name_for_isolation: &str) -> test_index::Index {
-        test_index::Index::new(
-            serde_saphyr::from_str(
-                &std::fs::read_to_string("src/test_index_config.yml")
-                    .unwrap()
-                    .replace("TEST_NAME", test_name_for_isolation),
-            )
-            .unwrap(),
-        )
-        .unwrap()
+        index
+            .lock_all_and_write(|transaction| {
+                transaction.public_remove_tag(&green.1)?;
+                transaction.public_insert(&o3, std::slice::from_ref(&Id([15; 16])))?;
+                assert_eq!(
+                    transaction.public_suggest(b"color:", 10, false)?,
+                    [named(&grey), named(&red)]
+                );
+                Ok(())
+            })
+            .unwrap();
     }
 
     #[test]
-    fn test_simple() {
-        let mut index = new_default_index("test_simple");
+    fn test_tag_names() {
+        let mut index = new_index("test_tag_names", |config| {
+            config.maintain_tag_dictionary_table = true;
+            config.maintain_tag_name_table = true;
+        });
 
-        let t1 = Id([11; 16]);
-        let t2 = Id([12; 16]);
-        let t3 = Id([13; 16]);
+        let red = Id([11; 16]);
+        let big = Id([12; 16]);
+        let raw = Id([13; 16]);
         let o1 = Id([21; 16]);
         let o2 = Id([22; 16]);
-        let o3 = Id([23; 16]);
 
         index
             .lock_all_and_write(|transaction| {
                 transaction
-                    .public_insert(&o1, std::slice::from_ref(&t1))
-                    .unwrap()
-                    .public_insert(&o2, &[t1.clone(), t2.clone()])
-                    .unwrap()
-                    .public_insert(&o3, &[t1.clone(), t2.clone(), t3.clone()])
-                    .unwrap();
+                    .public_insert_named(
+                        &o1,
+                        &[("color:red", red.clone()), ("size:big", big.clone())],
+                    )?
+                    .public_insert(&o1, std::slice::from_ref(&raw))?
+                    .public_insert(&o2, std::slice::from_ref(&big))?;
                 assert_eq!(
-                    transaction
-                        .public_search(&[t1.clone(), t2.clone(), t3.clone()], &[], None)?
-                        .collect::<Vec<_>>()?,
-                    std::slice::from_ref(&o3)
+                    transaction.public_get_tag_names(&o1)?,
+                    [
+                        (red.clone(), Some(b"color:red".to_vec())),
+                        (big.clone(), Some(b"size:big".to_vec())),
+                        (raw.clone(), None)
+                    ]
                 );
                 assert_eq!(
-                    transaction
-                        .public_search(&[t1.clone(), t2.clone()], &[], None)?
-                        .collect::<Vec<_>>()?,
-                    [o2.clone(), o3.clone()]
+                    transaction.public_get_tag_names(&o2)?,
+                    [(big.clone(), Some(b"size:big".to_vec()))]
                 );
+
+                transaction.public_register_tag_names(&[(b"raw".to_vec(), raw.clone())])?;
                 assert_eq!(
-                    transaction
-                        .public_search(std::slice::from_ref(&t1), &[], None)?
-                        .collect::<Vec<_>>()?,
-                    [o1.clone(), o2.clone(), o3.clone()]
+                    transaction.public_get_tag_name(&raw)?,
+                    Some(b"raw".to_vec())
+                );
+                assert_eq!(
+                    transaction.public_suggest(b"r", 10, false)?,
+                    [(b"raw".to_vec(), raw.clone())]
                 );
 
+                transaction.public_remove_tag(&red)?;
+                assert_eq!(transaction.public_get_tag_name(&red)?, None);
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t1), std::slice::from_ref(&t1), None)?
-                        .collect::<Vec<_>>()?,
-                    []
+                        .public_suggest(b"", 10, false)?
+                        .into_iter()
+                        .map(|(name, _)| name)
+                        .collect::<Vec<_>>(),
+                    [b"raw".to_vec(), b"size:big".to_vec()]
                 );
-                assert_eq!(
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_attributes() {
+        let mut index = new_index("test_attributes", |config| {
+            config.maintain_attribute_tables = true;
+        });
+
+        let year = Id([31; 16]);
+        let temperature = Id([32; 16]);
+        let book = Id([41; 16]);
+        let draft = Id([42; 16]);
+        let o1 = Id([1; 16]);
+        let o2 = Id([2; 16]);
+        let o3 = Id([3; 16]);
+        let o4 = Id([4; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, std::slice::from_ref(&book))?
+                    .public_insert(&o2, &[book.clone(), draft.clone()])?
+                    .public_insert(&o3, std::slice::from_ref(&book))?
+                    .public_set_attribute(&o1, &year, 1999u64)?
+                    .public_set_attribute(&o2, &year, 2005u64)?
+                    .public_set_attribute(&o3, &year, 300u64)?
+                    .public_set_attribute(&o4, &year, 2001u64)?
+                    .public_set_attribute(&o1, &temperature, -12.5f64)?
+                    .public_set_attribute(&o2, &temperature, 3.0f64)?
+                    .public_set_attribute(&o3, &temperature, -0.5f64)?;
+
+                let search = |transaction: &test_index::WriteTransaction<'_, '_>,
+                              present_tags: &[Id],
+                              absent_tags: &[Id],
+                              attribute_ranges: &[AttributeRange],
+                              start_after_object: Option<Id>,
+                              direction: Direction|
+                 -> anyhow::Result<Vec<Id>> {
                     transaction
-                        .public_search(std::slice::from_ref(&t1), &[], Some(o3.clone()))?
-                        .collect::<Vec<_>>()?,
-                    []
+                        .public_search_with_attributes(
+                            present_tags,
+                            absent_tags,
+                            attribute_ranges,
+                            start_after_object,
+                            direction,
+                        )?
+                        .collect()
+                };
+                assert_eq!(
+                    search(transaction, &[], &[], &[], None, Direction::Ascending)?,
+                    [o1.clone(), o2.clone(), o3.clone(), o4.clone()]
                 );
                 assert_eq!(
-                    transaction
-                        .public_search(&[], &[], Some(o3.clone()))?
-                        .collect::<Vec<_>>()?,
+                    search(
+                        transaction,
+                        &[],
+                        std::slice::from_ref(&draft),
+                        &[],
+                        Some(o4.clone()),
+                        Direction::Descending
+                    )?,
+                    [o3.clone(), o1.clone()]
+                );
+                let modern = AttributeRange::new(year.clone(), 1990u64..);
+                assert_eq!(
+                    search(
+                        transaction,
+                        &[],
+                        &[],
+                        std::slice::from_ref(&modern),
+                        None,
+                        Direction::Ascending
+                    )?,
+                    [o1.clone(), o2.clone(), o4.clone()]
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        &[],
+                        &[],
+                        std::slice::from_ref(&modern),
+                        Some(o2.clone()),
+                        Direction::Descending
+                    )?,
+                    vec![o1.clone()]
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        std::slice::from_ref(&book),
+                        &[],
+                        std::slice::from_ref(&modern),
+                        None,
+                        Direction::Ascending
+                    )?,
+                    [o1.clone(), o2.clone()]
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        std::slice::from_ref(&book),
+                        std::slice::from_ref(&draft),
+                        std::slice::from_ref(&modern),
+                        None,
+                        Direction::Ascending
+                    )?,
+                    vec![o1.clone()]
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        std::slice::from_ref(&book),
+                        &[],
+                        &[
+                            AttributeRange::new(year.clone(), ..=2000u64),
+                            AttributeRange::new(temperature.clone(), -1.0f64..)
+                        ],
+                        None,
+                        Direction::Ascending
+                    )?,
+                    vec![o3.clone()]
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        &[],
+                        &[],
+                        &[AttributeRange::new(year.clone(), 2001u64..2005)],
+                        None,
+                        Direction::Ascending
+                    )?,
+                    vec![o4.clone()]
+                );
+
+                assert_eq!(
+                    transaction.public_get_attribute::<u64>(&o3, &year)?,
+                    Some(300)
+                );
+                transaction.public_set_attribute(&o3, &year, 2020u64)?;
+                assert_eq!(
+                    transaction.public_get_attribute::<u64>(&o3, &year)?,
+                    Some(2020)
+                );
+                assert_eq!(
+                    search(
+                        transaction,
+                        &[],
+                        &[],
+                        &[AttributeRange::new(year.clone(), ..1000u64)],
+                        None,
+                        Direction::Ascending
+                    )?,
                     []
                 );
                 assert_eq!(
+                    transaction.public_get_attribute::<f64>(&o1, &temperature)?,
+                    Some(-12.5)
+                );
+
+                assert!(transaction.public_remove_attribute(&o4, &year)?);
+                assert!(!transaction.public_remove_attribute(&o4, &year)?);
+                assert_eq!(transaction.public_get_attribute::<u64>(&o4, &year)?, None);
+
+                transaction.public_remove_object(&o2)?;
+                assert_eq!(transaction.public_get_attributes(&o2)?, []);
+                assert_eq!(
+                    search(
+                        transaction,
+                        &[],
+                        &[],
+                        std::slice::from_ref(&modern),
+                        None,
+                        Direction::Ascending
+                    )?,
+                    [o1.clone(), o3.clone()]
+                );
+                assert_eq!(
+                    search(transaction, &[], &[], &[], None, Direction::Ascending)?,
+                    [o1.clone(), o3.clone()]
+                );
+                Ok(())
+            })
+            .unwrap();
+
+        index.config.maintain_attribute_tables = false;
+        assert!(index.migrate().is_err());
+        index
+            .lock_all_and_write(|transaction| {
+                assert!(
                     transaction
-                        .public_search(&[], &[t1.clone(), t2.clone(), t3.clone()], None)?
-                        .collect::<Vec<_>>()?,
-                    []
+                        .public_set_attribute(&o4, &year, 2001u64)
+                        .is_err()
+                );
+                assert!(
+                    transaction
+                        .public_search_with_attributes(
+                            &[],
+                            &[],
+                            &[AttributeRange::new(year.clone(), ..2000u64)],
+                            None,
+                            Direction::Ascending
+                        )
+                        .is_err()
                 );
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_attribute_range_streaming() {
+        let mut index = new_index("test_attribute_range_streaming", |config| {
+            config.maintain_attribute_tables = true;
+        });
+
+        // rank has a few distinct values, merged per value, while score has one per object, sorted at once
+        let rank = Id([31; 16]);
+        let score = Id([32; 16]);
+        let value = |attribute: &Id, i: usize| {
+            if *attribute == rank {
+                (i % 7) as i64 - 3
+            } else {
+                (i * 37 % 300) as i64 - 150
+            }
+        };
+        let objects = (0..300u16)
+            .map(|i| {
+                let mut object = [0u8; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for (i, object) in objects.iter().enumerate() {
+                    transaction
+                        .public_set_attribute(object, &rank, value(&rank, i))?
+                        .public_set_attribute(object, &score, value(&score, i))?;
+                }
+                Ok(())
+            })
+            .unwrap();
+        index
+            .lock_all_writes_and_read(|transaction| {
+                for (range, start_after_object, direction) in [
+                    (
+                        AttributeRange::new(rank.clone(), ..=i64::MAX),
+                        None,
+                        Direction::Ascending,
+                    ),
+                    (
+                        AttributeRange::new(
+                            rank.clone(),
+                            (
+                                std::ops::Bound::Excluded(-2i64),
+                                std::ops::Bound::Included(1),
+                            ),
+                        ),
+                        Some(objects[100].clone()),
+                        Direction::Ascending,
+                    ),
+                    (
+                        AttributeRange::new(rank.clone(), -1i64..2),
+                        Some(objects[200].clone()),
+                        Direction::Descending,
+                    ),
+                    (
+                        AttributeRange::new(score.clone(), ..=i64::MAX),
+                        None,
+                        Direction::Ascending,
+                    ),
+                    (
+                        AttributeRange::new(
+                            score.clone(),
+                            (
+                                std::ops::Bound::Excluded(-100i64),
+                                std::ops::Bound::Included(100),
+                            ),
+                        ),
+                        Some(objects[100].clone()),
+                        Direction::Ascending,
+                    ),
+                    (
+                        AttributeRange::new(score.clone(), -50i64..50),
+                        Some(objects[200].clone()),
+                        Direction::Descending,
+                    ),
+                    (
+                        AttributeRange::new(score.clone(), -3i64..3),
+                        None,
+                        Direction::Descending,
+                    ),
+                ] {
+                    let mut expected = objects
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, object)| {
+                            range.contains(&value(&range.attribute, *i).to_ordered_bytes())
+                                && start_after_object
+                                    .as_ref()
+                                    .is_none_or(|start_after_object| {
+                                        direction.precedes(start_after_object, *object)
+                                    })
+                        })
+                        .map(|(_, object)| object.clone())
+                        .collect::<Vec<_>>();
+                    if direction.is_descending() {
+                        expected.reverse();
+                    }
+                    assert_eq!(
+                        transaction
+                            .public_search_with_attributes(
+                                &[],
+                                &[],
+                                std::slice::from_ref(&range),
+                                start_after_object,
+                                direction
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected
+                    );
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_attribute_search_terms() {
+        let mut index = new_index("test_attribute_search_terms", |config| {
+            config.maintain_attribute_tables = true;
+        });
+
+        let rank = Id([31; 16]);
+        let score = Id([32; 16]);
+        let even = Id([41; 16]);
+        let third = Id([42; 16]);
+        let fifth = Id([43; 16]);
+        let rare = Id([44; 16]);
+        let tags_of = |i: usize| {
+            [(2, &even), (3, &third), (5, &fifth), (50, &rare)]
+                .into_iter()
+                .filter(|(divisor, _)| i.is_multiple_of(*divisor))
+                .map(|(_, tag)| tag.clone())
+                .collect::<Vec<_>>()
+        };
+        let value = |attribute: &Id, i: usize| {
+            if *attribute == rank {
+                (i % 7) as i64 - 3
+            } else {
+                (i * 37 % 300) as i64 - 150
+            }
+        };
+        let objects = (0..300u16)
+            .map(|i| {
+                let mut object = [0u8; 16];
+                object[..2].copy_from_slice(&i.to_be_bytes());
+                Id(object)
+            })
+            .collect::<Vec<_>>();
+
+        index
+            .lock_all_and_write(|transaction| {
+                for (i, object) in objects.iter().enumerate() {
+                    let tags = tags_of(i);
+                    if !tags.is_empty() {
+                        transaction.public_insert(object, &tags)?;
+                    }
+                    transaction
+                        .public_set_attribute(object, &rank, value(&rank, i))?
+                        .public_set_attribute(object, &score, value(&score, i))?;
+                }
+                Ok(())
+            })
+            .unwrap();
+
+        let rank_range = AttributeRange::new(rank.clone(), -1i64..2);
+        let score_range = AttributeRange::new(score.clone(), -100i64..100);
+        let whole_score_range = AttributeRange::new(score.clone(), ..=i64::MAX);
+        let cases = [
+            (
+                vec![even.clone(), third.clone()],
+                vec![rare.clone(), fifth.clone()],
+                vec![rank_range.clone()],
+                None,
+                Direction::Ascending,
+            ),
+            (
+                vec![],
+                vec![fifth.clone()],
+                vec![rank_range.clone(), score_range.clone()],
+                Some(objects[250].clone()),
+                Direction::Descending,
+            ),
+            (
+                vec![even.clone()],
+                vec![rare.clone()],
+                vec![whole_score_range.clone(), rank_range.clone()],
+                Some(objects[20].clone()),
+                Direction::Ascending,
+            ),
+        ];
+        index
+            .lock_all_writes_and_read(|transaction| {
+                for (present_tags, absent_tags, attribute_ranges, start_after_object, direction) in
+                    cases.iter()
+                {
+                    let mut expected = objects
+                        .iter()
+                        .enumerate()
+                        .filter(|(i, object)| {
+                            let tags = tags_of(*i);
+                            present_tags.iter().all(|tag| tags.contains(tag))
+                                && !absent_tags.iter().any(|tag| tags.contains(tag))
+                                && attribute_ranges.iter().all(|range| {
+                                    range.contains(&value(&range.attribute, *i).to_ordered_bytes())
+                                })
+                                && start_after_object
+                                    .as_ref()
+                                    .is_none_or(|start_after_object| {
+                                        direction.precedes(start_after_object, *object)
+                                    })
+                        })
+                        .map(|(_, object)| object.clone())
+                        .collect::<Vec<_>>();
+                    if direction.is_descending() {
+                        expected.reverse();
+                    }
+                    assert!(!expected.is_empty());
+                    assert_eq!(
+                        transaction
+                            .public_search_with_attributes(
+                                present_tags,
+                                absent_tags,
+                                attribute_ranges,
+                                start_after_object.clone(),
+                                *direction
+                            )?
+                            .collect::<Vec<_>>()?,
+                        expected
+                    );
 
+                    let mut paged = Vec::new();
+                    let mut token = Some(
+                        transaction
+                            .public_search_token_with_attributes(
+                                present_tags,
+                                absent_tags,
+                                attribute_ranges,
+                                *direction,
+                            )
+                            .to_string(),
+                    );
+                    if let Some(start_after_object) = start_after_object {
+                        token = token.map(|token| {
+                            token
+                                .parse::<SearchCursorToken>()
+                                .unwrap()
+                                .resumed_after(start_after_object.clone())
+                                .to_string()
+                        });
+                    }
+                    while let Some(current_token) = token {
+                        let (page, next_token) =
+                            transaction.public_search_page(&current_token.parse()?, 7)?;
+                        paged.extend(page);
+                        token = next_token.map(|next_token| next_token.to_string());
+                    }
+                    assert_eq!(paged, expected);
+                }
+                Ok(())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_query() {
+        let mut index = new_default_index("test_query");
+
+        let a = Id([11; 16]);
+        let b = Id([12; 16]);
+        let c = Id([13; 16]);
+        let d = Id([14; 16]);
+        let o1 = Id([21; 16]);
+        let o2 = Id([22; 16]);
+        let o3 = Id([23; 16]);
+        let o4 = Id([24; 16]);
+        let o5 = Id([25; 16]);
+
+        index
+            .lock_all_and_write(|transaction| {
+                transaction
+                    .public_insert(&o1, &[a.clone(), b.clone()])?
+                    .public_insert(&o2, std::slice::from_ref(&c))?
+                    .public_insert(&o3, &[c.clone(), d.clone()])?
+                    .public_insert(&o4, &[a.clone(), c.clone()])?
+                    .public_insert(&o5, std::slice::from_ref(&d))?;
+
+                let query = (Query::Tag(a.clone()) & Query::Tag(b.clone()))
+                    | (Query::Tag(c.clone()) & !Query::Tag(d.clone()));
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t1), std::slice::from_ref(&t2), None)?
+                        .public_query(&query, None, Direction::Ascending)?
                         .collect::<Vec<_>>()?,
-                    std::slice::from_ref(&o1)
+                    [o1.clone(), o2.clone(), o4.clone()]
                 );
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t1), std::slice::from_ref(&t3), None)?
+                        .public_query(&query, Some(o1.clone()), Direction::Ascending)?
                         .collect::<Vec<_>>()?,
-                    [o1.clone(), o2.clone()]
+                    [o2.clone(), o4.clone()]
                 );
-
-                transaction.public_remove_tags_from_object(&o3, &[t1.clone(), t3.clone()])?;
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t1), &[], None)?
+                        .public_query(&!Query::Tag(c.clone()), None, Direction::Ascending)?
                         .collect::<Vec<_>>()?,
-                    [o1.clone(), o2.clone()]
+                    [o1.clone(), o5.clone()]
                 );
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t2), &[], None)?
+                        .public_query(
+                            &((Query::Tag(a.clone()) | Query::Tag(d.clone()))
+                                & !(Query::Tag(b.clone()) | Query::Tag(c.clone()))),
+                            None,
+                            Direction::Ascending
+                        )?
                         .collect::<Vec<_>>()?,
-                    [o2.clone(), o3.clone()]
+                    std::slice::from_ref(&o5)
                 );
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t3), &[], None)?
+                        .public_query(
+                            &(Query::Tag(c.clone())
+                                & (Query::Tag(a.clone()) | Query::Tag(d.clone()))),
+                            None,
+                            Direction::Ascending
+                        )?
                         .collect::<Vec<_>>()?,
-                    []
+                    [o3.clone(), o4.clone()]
                 );
 
-                transaction.public_remove_object(&o2)?;
+                let nested = Query::And(vec![
+                    Query::And(vec![Query::Tag(c.clone())]),
+                    Query::Not(Box::new(Query::Or(vec![
+                        Query::Tag(a.clone()),
+                        Query::Not(Box::new(Query::Not(Box::new(Query::Tag(d.clone()))))),
+                    ]))),
+                ]);
+                let conjuncts = nested.conjuncts();
+                assert_eq!(conjuncts.present_tags, std::slice::from_ref(&c));
+                assert_eq!(conjuncts.absent_tags, [a.clone(), d.clone()]);
+                assert!(conjuncts.included.is_empty() && conjuncts.excluded.is_empty());
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t1), &[], None)?
+                        .public_query(&nested, None, Direction::Ascending)?
                         .collect::<Vec<_>>()?,
-                    std::slice::from_ref(&o1)
+                    std::slice::from_ref(&o2)
                 );
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t2), &[], None)?
+                        .public_query(&nested, None, Direction::Descending)?
                         .collect::<Vec<_>>()?,
-                    std::slice::from_ref(&o3)
+                    transaction
+                        .public_search(
+                            std::slice::from_ref(&c),
+                            &[a.clone(), d.clone()],
+                            None,
+                            Direction::Descending
+                        )?
+                        .collect::<Vec<_>>()?
                 );
                 assert_eq!(
                     transaction
-                        .public_search(std::slice::from_ref(&t3), &[], None)?
+                        .public_query(
+                            &!(Query::Tag(a.clone()) | Query::Tag(c.clone())),
+                            None,
+                            Direction::Ascending
+                        )?
                         .collect::<Vec<_>>()?,
-                    []
+                    std::slice::from_ref(&o5)
                 );
                 Ok(())
             })
@@ -736,14 +4897,19 @@ mod tests {
                     }
                     assert_eq!(
                         transaction
-                            .public_search(std::slice::from_ref(tag), &[], None)?
+                            .public_search(
+                                std::slice::from_ref(tag),
+                                &[],
+                                None,
+                                Direction::Ascending
+                            )?
                             .collect::<BTreeSet<_>>()?,
                         BTreeSet::from_iter(objects.iter().cloned())
                     );
                 }
 
                 let mut unrestricted_search_result = transaction
-                    .public_search(&[], &[], None)?
+                    .public_search(&[], &[], None, Direction::Ascending)?
                     .collect::<Vec<_>>()?;
                 unrestricted_search_result.sort();
                 let mut all_objects = object_to_tags.keys().cloned().collect::<Vec<_>>();
@@ -755,6 +4921,7 @@ mod tests {
                         &[],
                         &[],
                         Some(Id([0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0])),
+                        Direction::Ascending,
                     )?
                     .collect::<Vec<_>>()?;
                 nearly_unrestricted_search_result.sort();
@@ -767,7 +4934,7 @@ mod tests {
                     let present_tags = tags.iter().take(2).cloned().collect::<Vec<_>>();
                     let result = BTreeSet::from_iter(
                         transaction
-                            .public_search(&present_tags, &[], None)?
+                            .public_search(&present_tags, &[], None, Direction::Ascending)?
                             .collect::<Vec<_>>()?,
                     );
                     let correct = present_tags
@@ -0,0 +1,367 @@
+use anyhow::Result;
+
+use crate::{Id, Query};
+
+/// parentheses and negations nested deeper are rejected instead of overflowing the stack
+pub const MAX_NESTING_DEPTH: usize = 128;
+
+pub trait TagResolver {
+    fn resolve(&self, name: &str) -> Result<Id>;
+}
+
+impl<F> TagResolver for F
+where
+    F: Fn(&str) -> Result<Id>,
+{
+    fn resolve(&self, name: &str) -> Result<Id> {
+        self(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryParseError {
+    /// byte offset in the source string
+    pub position: usize,
+    pub message: String,
+}
+
+impl std::fmt::Display for QueryParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for QueryParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    OpeningParenthesis,
+    ClosingParenthesis,
+    Term {
+        field: Option<String>,
+        value: String,
+    },
+}
+
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, QueryParseError> {
+    let mut result = Vec::new();
+    let mut characters = text.char_indices().peekable();
+    while let Some(&(position, character)) = characters.peek() {
+        let token = match character {
+            _ if character.is_whitespace() => {
+                characters.next();
+                continue;
+            }
+            '&' => Token::And,
+            '|' => Token::Or,
+            '!' => Token::Not,
+            '(' => Token::OpeningParenthesis,
+            ')' => Token::ClosingParenthesis,
+            _ => {
+                let mut field = None;
+                let mut value = String::new();
+                while let Some(&(current_position, current_character)) = characters.peek() {
+                    match current_character {
+                        '"' if value.is_empty() => {
+                            characters.next();
+                            loop {
+                                match characters.next() {
+                                    Some((_, '"')) => break,
+                                    Some((escape_position, '\\')) => match characters.next() {
+                                        Some((_, escaped @ ('"' | '\\'))) => value.push(escaped),
+                                        _ => {
+                                            return Err(QueryParseError {
+                                                position: escape_position,
+                                                message:
+                                                    "Invalid escape sequence in quoted tag name"
+                                                        .to_string(),
+                                            });
+                                        }
+                                    },
+                                    Some((_, quoted_character)) => value.push(quoted_character),
+                                    None => {
+                                        return Err(QueryParseError {
+                                            position: current_position,
+                                            message: "Unterminated quoted tag name".to_string(),
+                                        });
+                                    }
+                                }
+                            }
+                            break;
+                        }
+                        ':' if field.is_none() => {
+                            characters.next();
+                            field = Some(std::mem::take(&mut value));
+                        }
+                        _ if current_character.is_whitespace()
+                            || "&|!()\"".contains(current_character) =>
+                        {
+                            break;
+                        }
+                        _ => {
+                            characters.next();
+                            value.push(current_character);
+                        }
+                    }
+                }
+                if value.is_empty() {
+                    return Err(QueryParseError {
+                        position,
+                        message: "Empty tag name".to_string(),
+                    });
+                }
+                result.push((position, Token::Term { field, value }));
+                continue;
+            }
+        };
+        characters.next();
+        result.push((position, token));
+    }
+    Ok(result)
+}
+
+struct Parser<'a, R: TagResolver> {
+    tokens: Vec<(usize, Token)>,
+    current: usize,
+    end_position: usize,
+    depth: usize,
+    resolver: &'a R,
+}
+
+impl<'a, R: TagResolver> Parser<'a, R> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.current).map(|(_, token)| token)
+    }
+
+    fn position(&self) -> usize {
+        self.tokens
+            .get(self.current)
+            .map(|(position, _)| *position)
+            .unwrap_or(self.end_position)
+    }
+
+    fn error(&self, message: &str) -> QueryParseError {
+        QueryParseError {
+            position: self.position(),
+            message: message.to_string(),
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Query, QueryParseError> {
+        let mut result = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.current += 1;
+            result = result | self.parse_and()?;
+        }
+        Ok(result)
+    }
+
+    fn parse_and(&mut self) -> Result<Query, QueryParseError> {
+        let mut result = self.parse_unary()?;
+        while self.peek() == Some(&Token::And) {
+            self.current += 1;
+            result = result & self.parse_unary()?;
+        }
+        Ok(result)
+    }
+
+    // consumes opening token, which is pointed to when nesting is too deep
+    fn parse_nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Query, QueryParseError>,
+    ) -> Result<Query, QueryParseError> {
+        if self.depth == MAX_NESTING_DEPTH {
+            return Err(self.error(&format!(
+                "Nesting is deeper than {MAX_NESTING_DEPTH} levels"
+            )));
+        }
+        self.current += 1;
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+        result
+    }
+
+    fn parse_unary(&mut self) -> Result<Query, QueryParseError> {
+        let position = self.position();
+        match self
+            .tokens
+            .get(self.current)
+            .map(|(_, token)| token.clone())
+        {
+            Some(Token::Not) => Ok(!self.parse_nested(Self::parse_unary)?),
+            Some(Token::OpeningParenthesis) => {
+                let result = self.parse_nested(Self::parse_or)?;
+                if self.peek() != Some(&Token::ClosingParenthesis) {
+                    return Err(self.error("Expected closing parenthesis"));
+                }
+                self.current += 1;
+                Ok(result)
+            }
+            Some(Token::Term { field, value }) => {
+                if let Some(field) = field
+                    && field != "tag"
+                {
+                    return Err(QueryParseError {
+                        position,
+                        message: format!("Unknown field {field:?}, only \"tag\" is supported"),
+                    });
+                }
+                self.current += 1;
+                Ok(Query::Tag(self.resolver.resolve(&value).map_err(
+                    |error| QueryParseError {
+                        position,
+                        message: format!("Can not resolve tag {value:?}: {error}"),
+                    },
+                )?))
+            }
+            Some(_) => Err(self.error("Expected tag, negation or opening parenthesis")),
+            None => Err(self.error("Unexpected end of query")),
+        }
+    }
+}
+
+fn parser<'a, R: TagResolver>(
+    text: &str,
+    resolver: &'a R,
+) -> Result<Parser<'a, R>, QueryParseError> {
+    Ok(Parser {
+        tokens: tokenize(text)?,
+        current: 0,
+        end_position: text.len(),
+        depth: 0,
+        resolver,
+    })
+}
+
+/// Parses expressions like `tag:red & (tag:big | !tag:sold)`, `!` binding tighter than `&` and `&` tighter than `|`
+pub fn parse_query<R: TagResolver>(text: &str, resolver: &R) -> Result<Query, QueryParseError> {
+    let mut parser = parser(text, resolver)?;
+    let result = parser.parse_or()?;
+    if parser.current != parser.tokens.len() {
+        return Err(parser.error("Unexpected token"));
+    }
+    Ok(result)
+}
+
+/// Parses query which must be a conjunction of tags and negated tags into present and absent tags lists;
+/// the error points to the first operand or `|` which breaks the conjunction
+pub fn parse_search<R: TagResolver>(
+    text: &str,
+    resolver: &R,
+) -> Result<(Vec<Id>, Vec<Id>), QueryParseError> {
+    let not_expressible = |position| QueryParseError {
+        position,
+        message: "Query can not be expressed as present and absent tags lists".to_string(),
+    };
+    let mut parser = parser(text, resolver)?;
+    let mut present_tags = Vec::new();
+    let mut absent_tags = Vec::new();
+    loop {
+        let position = parser.position();
+        let (operand_present_tags, operand_absent_tags) = parser
+            .parse_unary()?
+            .to_present_and_absent_tags()
+            .ok_or_else(|| not_expressible(position))?;
+        present_tags.extend(operand_present_tags);
+        absent_tags.extend(operand_absent_tags);
+        match parser.peek() {
+            Some(Token::And) => parser.current += 1,
+            Some(Token::Or) => return Err(not_expressible(parser.position())),
+            Some(_) => return Err(parser.error("Unexpected token")),
+            None => return Ok((present_tags, absent_tags)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn resolve(name: &str) -> Result<Id> {
+        match name {
+            "red" => Ok(Id([1; 16])),
+            "big" => Ok(Id([2; 16])),
+            "sold" => Ok(Id([3; 16])),
+            "dark blue" => Ok(Id([4; 16])),
+            _ => Err(anyhow::anyhow!("unknown tag")),
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let red = Query::Tag(Id([1; 16]));
+        let big = Query::Tag(Id([2; 16]));
+        let sold = Query::Tag(Id([3; 16]));
+        let dark_blue = Query::Tag(Id([4; 16]));
+
+        assert_eq!(
+            parse_query("tag:red & tag:big & !tag:sold", &resolve).unwrap(),
+            red.clone() & big.clone() & !sold.clone()
+        );
+        assert_eq!(
+            parse_query("red | big & !(sold | tag:\"dark blue\")", &resolve).unwrap(),
+            red.clone() | (big.clone() & !(sold.clone() | dark_blue.clone()))
+        );
+        assert_eq!(
+            parse_search("tag:red & tag:big & !tag:sold", &resolve).unwrap(),
+            (vec![Id([1; 16]), Id([2; 16])], vec![Id([3; 16])])
+        );
+        assert_eq!(
+            parse_search("!sold", &resolve).unwrap(),
+            (vec![], vec![Id([3; 16])])
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let error = |text| parse_query(text, &resolve).unwrap_err();
+
+        assert_eq!(error("tag:red &").position, 9);
+        assert_eq!(error("tag:red & tag:green").position, 10);
+        assert_eq!(error("(tag:red").position, 8);
+        assert_eq!(error("tag:red)").position, 7);
+        assert_eq!(error("color:red").position, 0);
+        assert_eq!(error("tag:\"red").position, 4);
+        assert_eq!(error("red & & big").position, 6);
+
+        let search_error = |text| parse_search(text, &resolve).unwrap_err().position;
+        assert_eq!(search_error("red | big"), 4);
+        assert_eq!(search_error("red & big | sold"), 10);
+        assert_eq!(search_error("red & !(big | sold)"), 6);
+        assert_eq!(search_error("red & (big | sold) & !red"), 6);
+        assert_eq!(search_error("red & big)"), 9);
+        assert_eq!(
+            parse_search("(red & !big) & !!sold", &resolve).unwrap(),
+            (vec![Id([1; 16]), Id([3; 16])], vec![Id([2; 16])])
+        );
+    }
+
+    #[test]
+    fn test_nesting_limit() {
+        let nested = |depth| format!("{}red{}", "(".repeat(depth), ")".repeat(depth));
+        assert_eq!(
+            parse_query(&nested(MAX_NESTING_DEPTH), &resolve).unwrap(),
+            Query::Tag(Id([1; 16]))
+        );
+        let error = parse_query(&nested(100_000), &resolve).unwrap_err();
+        assert_eq!(error.position, MAX_NESTING_DEPTH);
+        assert_eq!(
+            parse_query(&"!".repeat(100_000), &resolve)
+                .unwrap_err()
+                .position,
+            MAX_NESTING_DEPTH
+        );
+        assert_eq!(
+            parse_search(&format!("red & {}", nested(100_000)), &resolve)
+                .unwrap_err()
+                .position,
+            6 + MAX_NESTING_DEPTH
+        );
+    }
+}
@@ -0,0 +1,203 @@
+use crate::{AttributeRange, Direction, Id, bincode, encoding};
+
+const VERSION: u8 = 2;
+
+/// Tokens can be forged by clients, so decoding refuses to allocate more than this;
+/// fits a few thousands of tags, far more than any search uses
+pub const MAX_DECODED_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchCursorTokenError {
+    InvalidCharacter { position: usize },
+    Truncated,
+    ChecksumMismatch,
+    UnsupportedVersion(u8),
+    Malformed(String),
+    WrongSchema { expected: String, found: String },
+}
+
+impl std::fmt::Display for SearchCursorTokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::InvalidCharacter { position } => {
+                write!(
+                    f,
+                    "Search cursor token has invalid character at position {position}"
+                )
+            }
+            Self::Truncated => write!(f, "Search cursor token is truncated"),
+            Self::ChecksumMismatch => write!(f, "Search cursor token checksum does not match"),
+            Self::UnsupportedVersion(version) => {
+                write!(f, "Search cursor token version {version} is not supported")
+            }
+            Self::Malformed(reason) => write!(f, "Search cursor token is malformed: {reason}"),
+            Self::WrongSchema { expected, found } => write!(
+                f,
+                "Search cursor token refers to schema {found:?} while schema {expected:?} is searched"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SearchCursorTokenError {}
+
+/// Search query together with position to resume it from, serialized as unpadded base64url string
+#[derive(Debug, Clone, PartialEq, Eq, bincode::Encode, bincode::Decode)]
+#[bincode(crate = "bincode")]
+pub struct SearchCursorToken {
+    pub schema: String,
+    pub present_tags: Vec<Id>,
+    pub absent_tags: Vec<Id>,
+    pub attribute_ranges: Vec<AttributeRange>,
+    pub direction: Direction,
+    pub last_object: Option<Id>,
+}
+
+// FNV-1a, enough to tell accidentally damaged tokens apart from valid ones
+fn checksum(bytes: &[u8]) -> [u8; 4] {
+    bytes
+        .iter()
+        .fold(0x811c9dc5u32, |hash, byte| {
+            (hash ^ *byte as u32).wrapping_mul(0x01000193)
+        })
+        .to_be_bytes()
+}
+
+impl SearchCursorToken {
+    pub fn new(
+        schema: &str,
+        present_tags: &[Id],
+        absent_tags: &[Id],
+        direction: Direction,
+    ) -> Self {
+        Self {
+            schema: schema.to_string(),
+            present_tags: present_tags.to_vec(),
+            absent_tags: absent_tags.to_vec(),
+            attribute_ranges: Vec::new(),
+            direction,
+            last_object: None,
+        }
+    }
+
+    pub fn with_attribute_ranges(self, attribute_ranges: &[AttributeRange]) -> Self {
+        Self {
+            attribute_ranges: attribute_ranges.to_vec(),
+            ..self
+        }
+    }
+
+    pub fn resumed_after(&self, last_object: Id) -> Self {
+        Self {
+            last_object: Some(last_object),
+            ..self.clone()
+        }
+    }
+
+    pub fn check_schema(&self, expected: &str) -> Result<(), SearchCursorTokenError> {
+        if self.schema == expected {
+            Ok(())
+        } else {
+            Err(SearchCursorTokenError::WrongSchema {
+                expected: expected.to_string(),
+                found: self.schema.clone(),
+            })
+        }
+    }
+}
+
+impl std::fmt::Display for SearchCursorToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut bytes = vec![VERSION];
+        bincode::encode_into_std_write(self, &mut bytes, bincode::config::standard())
+            .map_err(|_| std::fmt::Error)?;
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        f.write_str(&encoding::encode_base64url(&bytes))
+    }
+}
+
+impl std::str::FromStr for SearchCursorToken {
+    type Err = SearchCursorTokenError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let bytes = encoding::decode_base64url(text)
+            .map_err(|position| SearchCursorTokenError::InvalidCharacter { position })?;
+        if bytes.len() < 1 + 4 {
+            return Err(SearchCursorTokenError::Truncated);
+        }
+        let (content, expected_checksum) = bytes.split_at(bytes.len() - 4);
+        if checksum(content) != expected_checksum {
+            return Err(SearchCursorTokenError::ChecksumMismatch);
+        }
+        if content[0] != VERSION {
+            return Err(SearchCursorTokenError::UnsupportedVersion(content[0]));
+        }
+        let (result, read) = bincode::decode_from_slice::<Self, _>(
+            &content[1..],
+            bincode::config::standard().with_limit::<MAX_DECODED_BYTES>(),
+        )
+        .map_err(|error| SearchCursorTokenError::Malformed(error.to_string()))?;
+        if read != content.len() - 1 {
+            return Err(SearchCursorTokenError::Malformed(format!(
+                "{} trailing bytes",
+                content.len() - 1 - read
+            )));
+        }
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn forge(content: &[u8]) -> String {
+        let mut bytes = vec![VERSION];
+        bytes.extend_from_slice(content);
+        let checksum = checksum(&bytes);
+        bytes.extend_from_slice(&checksum);
+        encoding::encode_base64url(&bytes)
+    }
+
+    fn huge_length() -> Vec<u8> {
+        let mut result = vec![0xfd];
+        result.extend_from_slice(&(1u64 << 60).to_le_bytes());
+        result
+    }
+
+    #[test]
+    fn test_forged_lengths() {
+        let mut huge_schema_name = huge_length();
+        huge_schema_name.extend_from_slice(b"public");
+        assert!(matches!(
+            forge(&huge_schema_name).parse::<SearchCursorToken>(),
+            Err(SearchCursorTokenError::Malformed(_))
+        ));
+
+        let mut huge_tags_list = vec![6];
+        huge_tags_list.extend_from_slice(b"public");
+        huge_tags_list.extend(huge_length());
+        assert!(matches!(
+            forge(&huge_tags_list).parse::<SearchCursorToken>(),
+            Err(SearchCursorTokenError::Malformed(_))
+        ));
+
+        let mut trailing = vec![VERSION];
+        bincode::encode_into_std_write(
+            SearchCursorToken::new("public", &[Id([1; 16])], &[], Direction::Ascending),
+            &mut trailing,
+            bincode::config::standard(),
+        )
+        .unwrap();
+        trailing.push(0);
+        assert_eq!(
+            forge(&trailing[1..]).parse::<SearchCursorToken>(),
+            Err(SearchCursorTokenError::Malformed(
+                "1 trailing bytes".to_string()
+            ))
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use anyhow::Error;
+use fallible_iterator::FallibleIterator;
+
+use crate::{Direction, Id};
+
+pub type Objects<'a> = Box<dyn FallibleIterator<Item = Id, Error = Error> + 'a>;
+
+// head of one of merged streams, ordered so that max-heap pops the one coming first in direction
+struct Head {
+    object: Id,
+    iterator_index: usize,
+    direction: Direction,
+}
+
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.direction {
+            Direction::Ascending => other.object.cmp(&self.object),
+            Direction::Descending => self.object.cmp(&other.object),
+        }
+        .then_with(|| other.iterator_index.cmp(&self.iterator_index))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// k-way merge of object streams sorted in given direction, yielding every object once in that direction;
+/// heads are kept in binary heap, so each object costs logarithm of streams count
+pub struct Union<'a> {
+    iterators: Vec<Objects<'a>>,
+    heads: BinaryHeap<Head>,
+    direction: Direction,
+    started: bool,
+}
+
+impl<'a> Union<'a> {
+    pub fn new(iterators: Vec<Objects<'a>>, direction: Direction) -> Self {
+        Self {
+            heads: BinaryHeap::with_capacity(iterators.len()),
+            iterators,
+            direction,
+            started: false,
+        }
+    }
+
+    fn advance(&mut self, iterator_index: usize) -> Result<(), Error> {
+        if let Some(object) = self.iterators[iterator_index].next()? {
+            self.heads.push(Head {
+                object,
+                iterator_index,
+                direction: self.direction,
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<'a> FallibleIterator for Union<'a> {
+    type Item = Id;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        if !self.started {
+            for iterator_index in 0..self.iterators.len() {
+                self.advance(iterator_index)?;
+            }
+            self.started = true;
+        }
+        let Some(first_head) = self.heads.pop() else {
+            return Ok(None);
+        };
+        self.advance(first_head.iterator_index)?;
+        while self
+            .heads
+            .peek()
+            .is_some_and(|head| head.object == first_head.object)
+        {
+            let head = self.heads.pop().unwrap();
+            self.advance(head.iterator_index)?;
+        }
+        Ok(Some(first_head.object))
+    }
+}
+
+/// leapfrog intersection of object streams sorted in given direction
+pub struct Intersection<'a> {
+    iterators: Vec<Objects<'a>>,
+    heads: Vec<Option<Id>>,
+    direction: Direction,
+    started: bool,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn new(iterators: Vec<Objects<'a>>, direction: Direction) -> Self {
+        Self {
+            heads: vec![None; iterators.len()],
+            iterators,
+            direction,
+            started: false,
+        }
+    }
+}
+
+impl<'a> FallibleIterator for Intersection<'a> {
+    type Item = Id;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        if self.iterators.is_empty() {
+            return Ok(None);
+        }
+        if !self.started {
+            for (head, iterator) in self.heads.iter_mut().zip(self.iterators.iter_mut()) {
+                *head = iterator.next()?;
+            }
+            self.started = true;
+        }
+        loop {
+            let mut candidate = None;
+            for head in self.heads.iter() {
+                match head {
+                    Some(head) => {
+                        if candidate
+                            .is_none_or(|candidate| self.direction.precedes(candidate, head))
+                        {
+                            candidate = Some(head);
+                        }
+                    }
+                    None => return Ok(None),
+                }
+            }
+            let candidate = candidate.unwrap().clone();
+            let mut all_equal = true;
+            for (head, iterator) in self.heads.iter_mut().zip(self.iterators.iter_mut()) {
+                while head
+                    .as_ref()
+                    .is_some_and(|head| self.direction.precedes(head, &candidate))
+                {
+                    *head = iterator.next()?;
+                }
+                match head {
+                    Some(head) => all_equal &= *head == candidate,
+                    None => return Ok(None),
+                }
+            }
+            if all_equal {
+                for (head, iterator) in self.heads.iter_mut().zip(self.iterators.iter_mut()) {
+                    *head = iterator.next()?;
+                }
+                return Ok(Some(candidate));
+            }
+        }
+    }
+}
+
+/// objects of `minuend` stream not present in `subtrahend` stream, both sorted in given direction
+pub struct Difference<'a> {
+    minuend: Objects<'a>,
+    subtrahend: Objects<'a>,
+    subtrahend_head: Option<Id>,
+    direction: Direction,
+    started: bool,
+}
+
+impl<'a> Difference<'a> {
+    pub fn new(minuend: Objects<'a>, subtrahend: Objects<'a>, direction: Direction) -> Self {
+        Self {
+            minuend,
+            subtrahend,
+            subtrahend_head: None,
+            direction,
+            started: false,
+        }
+    }
+}
+
+impl<'a> FallibleIterator for Difference<'a> {
+    type Item = Id;
+    type Error = Error;
+
+    fn next(&mut self) -> Result<Option<Self::Item>, Self::Error> {
+        if !self.started {
+            self.subtrahend_head = self.subtrahend.next()?;
+            self.started = true;
+        }
+        while let Some(object) = self.minuend.next()? {
+            while self
+                .subtrahend_head
+                .as_ref()
+                .is_some_and(|subtrahend_head| self.direction.precedes(subtrahend_head, &object))
+            {
+                self.subtrahend_head = self.subtrahend.next()?;
+            }
+            if self.subtrahend_head.as_ref() != Some(&object) {
+                return Ok(Some(object));
+            }
+        }
+        Ok(None)
+    }
+}
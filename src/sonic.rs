@@ -0,0 +1,1036 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, Result};
+
+use crate::{Id, TextTagger, hashing::Xxh3};
+
+const PROTOCOL_VERSION: usize = 1;
+const BUFFER_SIZE: usize = 20000;
+const QUERY_LIMIT_DEFAULT: usize = 10;
+const QUERY_LIMIT_MAXIMUM: usize = 100;
+const SUGGEST_LIMIT_DEFAULT: usize = 5;
+const SUGGEST_LIMIT_MAXIMUM: usize = 20;
+const CONNECTIONS_LIMIT_DEFAULT: usize = 64;
+
+/// Storage operations behind Sonic Channel commands, implemented for index by `define_sonic_backend!`
+pub trait SonicBackend {
+    fn has_collection(&self, collection: &str) -> bool;
+    fn push(&mut self, collection: &str, bucket: &str, object: &str, text: &str) -> Result<()>;
+    fn pop(&mut self, collection: &str, bucket: &str, object: &str, text: &str) -> Result<usize>;
+    fn query(
+        &self,
+        collection: &str,
+        bucket: &str,
+        terms: &str,
+        limit: usize,
+        offset: usize,
+    ) -> Result<Vec<String>>;
+    fn suggest(
+        &self,
+        collection: &str,
+        bucket: &str,
+        word: &str,
+        limit: usize,
+    ) -> Result<Vec<String>>;
+    // buckets in collection, objects in bucket or words of object, as sonic does
+    fn count(&self, collection: &str, bucket: Option<&str>, object: Option<&str>) -> Result<usize>;
+    fn flush_collection(&mut self, collection: &str) -> Result<usize>;
+    fn flush_bucket(&mut self, collection: &str, bucket: &str) -> Result<usize>;
+    fn flush_object(&mut self, collection: &str, bucket: &str, object: &str) -> Result<usize>;
+}
+
+pub fn bucket_tag(bucket: &str) -> Id {
+    Id::from_str_hash::<Xxh3>(&format!("bucket:{bucket}"))
+}
+
+pub fn word_tags(text: &str) -> Vec<Id> {
+    TextTagger::<Xxh3>::new().tags(text)
+}
+
+pub fn named_word_tags(text: &str) -> Vec<(String, Id)> {
+    TextTagger::<Xxh3>::new().named_tags(text)
+}
+
+pub fn object_id(collection: &str, bucket: &str, object: &str) -> Id {
+    Id::from_str_hash::<Xxh3>(&format!("{collection}\0{bucket}\0{object}"))
+}
+
+/// value stored in object names table
+pub fn encode_object_name(bucket: &str, object: &str) -> Vec<u8> {
+    format!("{bucket}\0{object}").into_bytes()
+}
+
+pub fn decode_object_name(encoded: &[u8]) -> Result<(String, String)> {
+    let encoded = std::str::from_utf8(encoded).with_context(|| "Object name is not UTF-8")?;
+    let (bucket, object) = encoded
+        .split_once('\0')
+        .with_context(|| format!("Object name {encoded:?} has no bucket"))?;
+    Ok((bucket.to_string(), object.to_string()))
+}
+
+/// Implements `SonicBackend` for index, mapping collections to its schemas;
+/// object names are kept in `object_name<Id, Vec<u8>>` table of given additional schema,
+/// words are suggested only if `maintain_tag_dictionary_table` is set in index config
+#[macro_export]
+macro_rules! define_sonic_backend {
+    ($index_name:ident(
+        $(
+            $schema_name:ident
+        )+
+    ) object_names: $object_names_schema_name:ident) => {
+        impl $crate::sonic::SonicBackend for $index_name::Index {
+            fn has_collection(&self, collection: &str) -> bool {
+                [$(stringify!($schema_name)),+].contains(&collection)
+            }
+
+            fn push(&mut self, collection: &str, bucket: &str, object: &str, text: &str) -> $crate::anyhow::Result<()> {
+                let object_id = $crate::sonic::object_id(collection, bucket, object);
+                let word_tags = $crate::sonic::named_word_tags(text);
+                let bucket_tag = $crate::sonic::bucket_tag(bucket);
+                self.lock_all_and_write(|transaction| {
+                    match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {
+                                transaction
+                                    .[<$schema_name _insert_named>](&object_id, &word_tags)?
+                                    .[<$schema_name _insert>](&object_id, std::slice::from_ref(&bucket_tag))?
+                            },
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    transaction
+                        .database_transaction
+                        .$object_names_schema_name
+                        .object_name
+                        .insert(object_id.clone(), $crate::sonic::encode_object_name(bucket, object));
+                    Ok(())
+                })
+            }
+
+            fn pop(&mut self, collection: &str, bucket: &str, object: &str, text: &str) -> $crate::anyhow::Result<usize> {
+                let object_id = $crate::sonic::object_id(collection, bucket, object);
+                let bucket_tag = $crate::sonic::bucket_tag(bucket);
+                let word_tags = $crate::sonic::word_tags(text);
+                self.lock_all_and_write(|transaction| {
+                    let (popped_count, remaining_tags) = match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {{
+                                let mut popped = Vec::new();
+                                for tag in word_tags.iter() {
+                                    if transaction.[<$schema_name _has_tag>](&object_id, tag)? {
+                                        popped.push(tag.clone());
+                                    }
+                                }
+                                transaction.[<$schema_name _remove_tags_from_object>](&object_id, &popped)?;
+                                let remaining_tags = transaction.[<$schema_name _get_tags>](&object_id)?;
+                                if remaining_tags == std::slice::from_ref(&bucket_tag) {
+                                    transaction.[<$schema_name _remove_object>](&object_id)?;
+                                }
+                                (popped.len(), remaining_tags)
+                            }},
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    if remaining_tags.len() <= 1 {
+                        transaction
+                            .database_transaction
+                            .$object_names_schema_name
+                            .object_name
+                            .remove(&object_id);
+                    }
+                    Ok(popped_count)
+                })
+            }
+
+            fn query(
+                &self,
+                collection: &str,
+                bucket: &str,
+                terms: &str,
+                limit: usize,
+                offset: usize,
+            ) -> $crate::anyhow::Result<Vec<String>> {
+                use $crate::fallible_iterator::FallibleIterator;
+
+                let mut present_tags = $crate::sonic::word_tags(terms);
+                if present_tags.is_empty() {
+                    return Ok(Vec::new());
+                }
+                present_tags.push($crate::sonic::bucket_tag(bucket));
+                self.lock_all_writes_and_read(|transaction| {
+                    let objects = match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {
+                                transaction
+                                    .[<$schema_name _search>](&present_tags, &[], None, $crate::Direction::Ascending)?
+                                    .skip(offset)
+                                    .take(limit)
+                                    .collect::<Vec<_>>()?
+                            },
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    let mut result = Vec::with_capacity(objects.len());
+                    for object_id in objects.iter() {
+                        if let Some((found_object_id, encoded)) = transaction
+                            .database_transaction
+                            .$object_names_schema_name
+                            .object_name
+                            .iter(std::ops::Bound::Included(object_id), false)?
+                            .next()?
+                            && found_object_id == *object_id
+                        {
+                            result.push($crate::sonic::decode_object_name(&encoded)?.1);
+                        }
+                    }
+                    Ok(result)
+                })
+            }
+
+            fn suggest(
+                &self,
+                collection: &str,
+                bucket: &str,
+                word: &str,
+                limit: usize,
+            ) -> $crate::anyhow::Result<Vec<String>> {
+                use $crate::fallible_iterator::FallibleIterator;
+
+                let bucket_tag = $crate::sonic::bucket_tag(bucket);
+                let prefix = word.to_lowercase();
+                self.lock_all_writes_and_read(|transaction| {
+                    Ok(match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {
+                                transaction
+                                    .[<$schema_name _tag_dictionary_entries>](prefix.as_bytes())?
+                                    .filter(|(_, tag)| Ok(transaction
+                                        .[<$schema_name _search>](&[bucket_tag.clone(), tag.clone()], &[], None, $crate::Direction::Ascending)?
+                                        .next()?
+                                        .is_some()))
+                                    .take(limit)
+                                    .map(|(name, _)| Ok(String::from_utf8_lossy(&name).into_owned()))
+                                    .collect::<Vec<_>>()?
+                            },
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    })
+                })
+            }
+
+            fn count(&self, collection: &str, bucket: Option<&str>, object: Option<&str>) -> $crate::anyhow::Result<usize> {
+                use $crate::fallible_iterator::FallibleIterator;
+
+                self.lock_all_writes_and_read(|transaction| {
+                    Ok(match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {
+                                match (bucket, object) {
+                                    (Some(bucket), Some(object)) => transaction
+                                        .[<$schema_name _get_tags>](&$crate::sonic::object_id(collection, bucket, object))?
+                                        .len()
+                                        .saturating_sub(1),
+                                    (Some(bucket), None) => transaction
+                                        .[<$schema_name _tag_cardinality>](&$crate::sonic::bucket_tag(bucket))? as usize,
+                                    _ => {
+                                        let mut buckets = std::collections::BTreeSet::new();
+                                        let mut objects = transaction.[<$schema_name _search>](&[], &[], None, $crate::Direction::Ascending)?;
+                                        while let Some(object_id) = objects.next()? {
+                                            if let Some((found_object_id, encoded)) = transaction
+                                                .database_transaction
+                                                .$object_names_schema_name
+                                                .object_name
+                                                .iter(std::ops::Bound::Included(&object_id), false)?
+                                                .next()?
+                                                && found_object_id == object_id
+                                            {
+                                                buckets.insert($crate::sonic::decode_object_name(&encoded)?.0);
+                                            }
+                                        }
+                                        buckets.len()
+                                    }
+                                }
+                            },
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    })
+                })
+            }
+
+            fn flush_collection(&mut self, collection: &str) -> $crate::anyhow::Result<usize> {
+                use $crate::fallible_iterator::FallibleIterator;
+
+                self.lock_all_and_write(|transaction| {
+                    let objects = match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {{
+                                let objects = transaction
+                                    .[<$schema_name _search>](&[], &[], None, $crate::Direction::Ascending)?
+                                    .collect::<Vec<_>>()?;
+                                for object_id in objects.iter() {
+                                    transaction.[<$schema_name _remove_object>](object_id)?;
+                                }
+                                objects
+                            }},
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    for object_id in objects.iter() {
+                        transaction
+                            .database_transaction
+                            .$object_names_schema_name
+                            .object_name
+                            .remove(object_id);
+                    }
+                    Ok(objects.len())
+                })
+            }
+
+            fn flush_bucket(&mut self, collection: &str, bucket: &str) -> $crate::anyhow::Result<usize> {
+                use $crate::fallible_iterator::FallibleIterator;
+
+                let bucket_tag = $crate::sonic::bucket_tag(bucket);
+                self.lock_all_and_write(|transaction| {
+                    let objects = match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {{
+                                let objects = transaction
+                                    .[<$schema_name _search>](std::slice::from_ref(&bucket_tag), &[], None, $crate::Direction::Ascending)?
+                                    .collect::<Vec<_>>()?;
+                                for object_id in objects.iter() {
+                                    transaction.[<$schema_name _remove_object>](object_id)?;
+                                }
+                                objects
+                            }},
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    for object_id in objects.iter() {
+                        transaction
+                            .database_transaction
+                            .$object_names_schema_name
+                            .object_name
+                            .remove(object_id);
+                    }
+                    Ok(objects.len())
+                })
+            }
+
+            fn flush_object(&mut self, collection: &str, bucket: &str, object: &str) -> $crate::anyhow::Result<usize> {
+                let object_id = $crate::sonic::object_id(collection, bucket, object);
+                self.lock_all_and_write(|transaction| {
+                    let words_count = match collection {
+                        $(
+                            stringify!($schema_name) => $crate::paste::paste! {{
+                                let words_count = transaction.[<$schema_name _get_tags>](&object_id)?.len().saturating_sub(1);
+                                transaction.[<$schema_name _remove_object>](&object_id)?;
+                                words_count
+                            }},
+                        )+
+                        _ => $crate::anyhow::bail!("Unknown collection {collection:?}"),
+                    };
+                    transaction
+                        .database_transaction
+                        .$object_names_schema_name
+                        .object_name
+                        .remove(&object_id);
+                    Ok(words_count)
+                })
+            }
+        }
+    };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Mode {
+    Search,
+    Ingest,
+    Control,
+}
+
+impl Mode {
+    fn commands(self) -> &'static [&'static str] {
+        match self {
+            Self::Search => &["QUERY", "SUGGEST", "PING", "HELP", "QUIT"],
+            Self::Ingest => &[
+                "PUSH", "POP", "COUNT", "FLUSHC", "FLUSHB", "FLUSHO", "PING", "HELP", "QUIT",
+            ],
+            Self::Control => &["TRIGGER", "INFO", "PING", "HELP", "QUIT"],
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Argument {
+    Word(String),
+    Text(String),
+    Meta(String, String),
+}
+
+// consumes input up to and including next line terminator
+fn skip_line(reader: &mut impl BufRead) -> std::io::Result<()> {
+    loop {
+        let available = reader.fill_buf()?;
+        if available.is_empty() {
+            return Ok(());
+        }
+        match available.iter().position(|byte| *byte == b'\n') {
+            Some(position) => {
+                reader.consume(position + 1);
+                return Ok(());
+            }
+            None => {
+                let length = available.len();
+                reader.consume(length);
+            }
+        }
+    }
+}
+
+fn parse_arguments(line: &str) -> Option<Vec<Argument>> {
+    let mut result = Vec::new();
+    let mut characters = line.chars().peekable();
+    while let Some(&character) = characters.peek() {
+        if character == ' ' {
+            characters.next();
+        } else if character == '"' {
+            characters.next();
+            let mut text = String::new();
+            loop {
+                match characters.next()? {
+                    '"' => break,
+                    '\\' => text.push(characters.next()?),
+                    character => text.push(character),
+                }
+            }
+            result.push(Argument::Text(text));
+        } else {
+            let mut word = String::new();
+            while let Some(&character) = characters.peek()
+                && character != ' '
+            {
+                word.push(character);
+                characters.next();
+            }
+            result.push(
+                match word.strip_suffix(')').and_then(|word| word.split_once('(')) {
+                    Some((name, value)) => Argument::Meta(name.to_string(), value.to_string()),
+                    None => Argument::Word(word),
+                },
+            );
+        }
+    }
+    Some(result)
+}
+
+#[derive(Default)]
+struct Statistics {
+    clients_connected: usize,
+    commands_total: usize,
+    command_latency_best: Option<Duration>,
+    command_latency_worst: Option<Duration>,
+}
+
+// counts connection as connected until dropped
+struct Connection {
+    statistics: Arc<Mutex<Statistics>>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        if let Ok(mut statistics) = self.statistics.lock() {
+            statistics.clients_connected -= 1;
+        }
+    }
+}
+
+/// Speaks Sonic Channel protocol, so sonic clients can use dream index instead
+pub struct SonicServer<B> {
+    backend: Arc<Mutex<B>>,
+    password: String,
+    connections_limit: usize,
+    started: Instant,
+    statistics: Arc<Mutex<Statistics>>,
+}
+
+impl<B> Clone for SonicServer<B> {
+    fn clone(&self) -> Self {
+        Self {
+            backend: self.backend.clone(),
+            password: self.password.clone(),
+            connections_limit: self.connections_limit,
+            started: self.started,
+            statistics: self.statistics.clone(),
+        }
+    }
+}
+
+impl<B: SonicBackend + Send + 'static> SonicServer<B> {
+    pub fn new(backend: B, password: &str) -> Self {
+        Self {
+            backend: Arc::new(Mutex::new(backend)),
+            password: password.to_string(),
+            connections_limit: CONNECTIONS_LIMIT_DEFAULT,
+            started: Instant::now(),
+            statistics: Arc::new(Mutex::new(Statistics::default())),
+        }
+    }
+
+    /// connections accepted by `serve` above this limit are ended right away, 64 by default
+    pub fn with_connections_limit(mut self, connections_limit: usize) -> Self {
+        self.connections_limit = connections_limit;
+        self
+    }
+
+    fn connect(&self, limit: usize) -> Result<Option<Connection>> {
+        let mut statistics = self
+            .statistics
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Sonic server statistics lock is poisoned"))?;
+        if statistics.clients_connected >= limit {
+            return Ok(None);
+        }
+        statistics.clients_connected += 1;
+        Ok(Some(Connection {
+            statistics: self.statistics.clone(),
+        }))
+    }
+
+    /// serves each connection in its own thread until listener fails
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            let mut stream = stream.with_context(|| "Can not accept Sonic Channel connection")?;
+            let Some(connection) = self.connect(self.connections_limit)? else {
+                let _ = writeln!(stream, "ENDED connections_limit\r");
+                continue;
+            };
+            let server = self.clone();
+            std::thread::spawn(move || {
+                let _connection = connection;
+                server.serve_connection(stream)
+            });
+        }
+        Ok(())
+    }
+
+    pub fn handle_connection(&self, stream: TcpStream) -> Result<()> {
+        let _connection = self.connect(usize::MAX)?;
+        self.serve_connection(stream)
+    }
+
+    fn serve_connection(&self, stream: TcpStream) -> Result<()> {
+        let mut writer = stream
+            .try_clone()
+            .with_context(|| "Can not clone Sonic Channel connection stream")?;
+        let mut reader = BufReader::new(stream);
+        writeln!(writer, "CONNECTED <dream v{}>\r", env!("CARGO_PKG_VERSION"))?;
+        let mut mode = None;
+        let mut marker = 0usize;
+        let mut buffer = Vec::with_capacity(BUFFER_SIZE);
+        loop {
+            buffer.clear();
+            // line with its terminator must fit into buffer, the rest of longer one is skipped without being stored
+            if (&mut reader)
+                .take(BUFFER_SIZE as u64)
+                .read_until(b'\n', &mut buffer)
+                .with_context(|| "Can not read Sonic Channel command")?
+                == 0
+            {
+                break;
+            }
+            if buffer.len() == BUFFER_SIZE && !buffer.ends_with(b"\n") {
+                skip_line(&mut reader).with_context(|| "Can not skip Sonic Channel command")?;
+                writeln!(writer, "ERR buffer_overflow\r")?;
+                continue;
+            }
+            let Ok(line) = std::str::from_utf8(&buffer) else {
+                writeln!(writer, "ERR invalid_format(command is not UTF-8)\r")?;
+                continue;
+            };
+            let line = line.trim_end_matches(['\r', '\n']);
+            let (command, arguments) = line.split_once(' ').unwrap_or((line, ""));
+            let command = command.to_uppercase();
+            let Some(arguments) = parse_arguments(arguments) else {
+                writeln!(writer, "ERR invalid_format(unterminated text)\r")?;
+                continue;
+            };
+            let responses = match (mode, command.as_str()) {
+                (_, "QUIT") => {
+                    writeln!(writer, "ENDED quit\r")?;
+                    return Ok(());
+                }
+                (_, "PING") => vec!["PONG".to_string()],
+                (None, "START") => match arguments.as_slice() {
+                    [Argument::Word(requested_mode), rest @ ..] if rest.len() <= 1 => {
+                        let password = match rest {
+                            [Argument::Word(password)] => password.as_str(),
+                            _ => "",
+                        };
+                        if password != self.password {
+                            writeln!(writer, "ENDED authentication_failed\r")?;
+                            return Ok(());
+                        }
+                        mode = match requested_mode.as_str() {
+                            "search" => Some(Mode::Search),
+                            "ingest" => Some(Mode::Ingest),
+                            "control" => Some(Mode::Control),
+                            _ => {
+                                writeln!(writer, "ENDED invalid_mode\r")?;
+                                return Ok(());
+                            }
+                        };
+                        vec![format!(
+                            "STARTED {requested_mode} protocol({PROTOCOL_VERSION}) buffer({BUFFER_SIZE})"
+                        )]
+                    }
+                    _ => vec!["ERR invalid_format(START <mode> <password>)".to_string()],
+                },
+                (None, _) => vec!["ERR invalid_mode(not_started)".to_string()],
+                (Some(mode), "HELP") => {
+                    vec![format!("RESULT commands({})", mode.commands().join(", "))]
+                }
+                (Some(mode), _) if !mode.commands().contains(&command.as_str()) => {
+                    vec!["ERR unknown_command".to_string()]
+                }
+                (Some(_), _) => {
+                    marker += 1;
+                    self.execute(&command, &arguments, &format!("{marker:08x}"))
+                }
+            };
+            for response in responses {
+                writeln!(writer, "{response}\r")?;
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&self, command: &str, arguments: &[Argument], marker: &str) -> Vec<String> {
+        let started = Instant::now();
+        let responses = match self.try_execute(command, arguments, marker) {
+            Ok(responses) => responses,
+            Err(error) => vec![format!("ERR internal_error({error})")],
+        };
+        let latency = started.elapsed();
+        if let Ok(mut statistics) = self.statistics.lock() {
+            statistics.commands_total += 1;
+            statistics.command_latency_best = Some(
+                statistics
+                    .command_latency_best
+                    .map_or(latency, |best| best.min(latency)),
+            );
+            statistics.command_latency_worst = Some(
+                statistics
+                    .command_latency_worst
+                    .map_or(latency, |worst| worst.max(latency)),
+            );
+        }
+        responses
+    }
+
+    // only counters dream keeps, sonic storage specific ones are omitted
+    fn info(&self) -> Result<String> {
+        let statistics = self
+            .statistics
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Sonic server statistics lock is poisoned"))?;
+        let milliseconds = |latency: Option<Duration>| latency.unwrap_or_default().as_millis();
+        Ok(format!(
+            "RESULT uptime({}) clients_connected({}) commands_total({}) command_latency_best({}) command_latency_worst({})",
+            self.started.elapsed().as_secs(),
+            statistics.clients_connected,
+            statistics.commands_total,
+            milliseconds(statistics.command_latency_best),
+            milliseconds(statistics.command_latency_worst),
+        ))
+    }
+
+    fn try_execute(
+        &self,
+        command: &str,
+        arguments: &[Argument],
+        marker: &str,
+    ) -> Result<Vec<String>> {
+        let mut backend = self
+            .backend
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Sonic backend lock is poisoned"))?;
+        let collection = match arguments.first() {
+            Some(Argument::Word(collection)) => Some(collection.as_str()),
+            _ => None,
+        };
+        if let Some(collection) = collection
+            && !backend.has_collection(collection)
+        {
+            return Ok(vec![format!("ERR unknown_collection({collection})")]);
+        }
+        let limit = |name: &str, default: usize, maximum: usize| -> Result<usize> {
+            Ok(arguments
+                .iter()
+                .find_map(|argument| match argument {
+                    Argument::Meta(meta_name, value) if meta_name == name => Some(value.parse()),
+                    _ => None,
+                })
+                .transpose()
+                .with_context(|| format!("Can not parse {name}"))?
+                .unwrap_or(default)
+                .min(maximum))
+        };
+        let result = |count: usize| vec![format!("RESULT {count}")];
+        Ok(match (command, arguments) {
+            (
+                "QUERY",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Text(terms),
+                    ..,
+                ],
+            ) => {
+                let objects = backend.query(
+                    collection,
+                    bucket,
+                    terms,
+                    limit("LIMIT", QUERY_LIMIT_DEFAULT, QUERY_LIMIT_MAXIMUM)?,
+                    limit("OFFSET", 0, usize::MAX)?,
+                )?;
+                vec![
+                    format!("PENDING {marker}"),
+                    format!("EVENT QUERY {marker} {}", objects.join(" ")),
+                ]
+            }
+            (
+                "SUGGEST",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Text(word),
+                    ..,
+                ],
+            ) => {
+                let words = backend.suggest(
+                    collection,
+                    bucket,
+                    word,
+                    limit("LIMIT", SUGGEST_LIMIT_DEFAULT, SUGGEST_LIMIT_MAXIMUM)?,
+                )?;
+                vec![
+                    format!("PENDING {marker}"),
+                    format!("EVENT SUGGEST {marker} {}", words.join(" ")),
+                ]
+            }
+            (
+                "PUSH",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Word(object),
+                    Argument::Text(text),
+                    ..,
+                ],
+            ) => {
+                backend.push(collection, bucket, object, text)?;
+                vec!["OK".to_string()]
+            }
+            (
+                "POP",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Word(object),
+                    Argument::Text(text),
+                ],
+            ) => result(backend.pop(collection, bucket, object, text)?),
+            ("COUNT", [Argument::Word(collection)]) => {
+                result(backend.count(collection, None, None)?)
+            }
+            ("COUNT", [Argument::Word(collection), Argument::Word(bucket)]) => {
+                result(backend.count(collection, Some(bucket), None)?)
+            }
+            (
+                "COUNT",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Word(object),
+                ],
+            ) => result(backend.count(collection, Some(bucket), Some(object))?),
+            ("FLUSHC", [Argument::Word(collection)]) => {
+                result(backend.flush_collection(collection)?)
+            }
+            ("FLUSHB", [Argument::Word(collection), Argument::Word(bucket)]) => {
+                result(backend.flush_bucket(collection, bucket)?)
+            }
+            (
+                "FLUSHO",
+                [
+                    Argument::Word(collection),
+                    Argument::Word(bucket),
+                    Argument::Word(object),
+                ],
+            ) => result(backend.flush_object(collection, bucket, object)?),
+            ("TRIGGER", _) => vec!["OK".to_string()],
+            ("INFO", []) => vec![self.info()?],
+            _ => vec![format!("ERR invalid_format({})", command_format(command))],
+        })
+    }
+}
+
+fn command_format(command: &str) -> &'static str {
+    match command {
+        "QUERY" => {
+            "QUERY <collection> <bucket> \"<terms>\" [LIMIT(<count>)]? [OFFSET(<count>)]? [LANG(<locale>)]?"
+        }
+        "SUGGEST" => "SUGGEST <collection> <bucket> \"<word>\" [LIMIT(<count>)]?",
+        "PUSH" => "PUSH <collection> <bucket> <object> \"<text>\" [LANG(<locale>)]?",
+        "POP" => "POP <collection> <bucket> <object> \"<text>\"",
+        "COUNT" => "COUNT <collection> [<bucket> [<object>]?]?",
+        "FLUSHC" => "FLUSHC <collection>",
+        "FLUSHB" => "FLUSHB <collection> <bucket>",
+        "FLUSHO" => "FLUSHO <collection> <bucket> <object>",
+        "INFO" => "INFO",
+        _ => "",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    crate::define_index!(sonic_test_index(
+        messages
+    ) {
+        names {
+            object_name<Id, Vec<u8>>
+        }
+    } use {
+    });
+
+    crate::define_sonic_backend!(sonic_test_index(messages) object_names: names);
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn open(address: std::net::SocketAddr) -> Self {
+            let stream = TcpStream::connect(address).unwrap();
+            let mut result = Self {
+                writer: stream.try_clone().unwrap(),
+                reader: BufReader::new(stream),
+            };
+            assert!(result.read().starts_with("CONNECTED "));
+            result
+        }
+
+        fn connect(address: std::net::SocketAddr, mode: &str) -> Self {
+            let mut result = Self::open(address);
+            assert_eq!(
+                result.send(&format!("START {mode} secret")),
+                format!("STARTED {mode} protocol(1) buffer(20000)")
+            );
+            result
+        }
+
+        fn read(&mut self) -> String {
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            line.trim_end().to_string()
+        }
+
+        fn send(&mut self, command: &str) -> String {
+            self.send_bytes(command.as_bytes())
+        }
+
+        fn send_bytes(&mut self, command: &[u8]) -> String {
+            self.writer.write_all(command).unwrap();
+            self.writer.write_all(b"\r\n").unwrap();
+            self.read()
+        }
+
+        fn query(&mut self, command: &str) -> String {
+            let pending = self.send(command);
+            let marker = pending.strip_prefix("PENDING ").unwrap().to_string();
+            let event = command.split(' ').next().unwrap();
+            self.read()
+                .strip_prefix(&format!("EVENT {event} {marker}"))
+                .unwrap()
+                .trim()
+                .to_string()
+        }
+    }
+
+    #[test]
+    fn test_parse_arguments() {
+        assert_eq!(
+            parse_arguments("messages user:1 \"hello \\\"world\\\"\" LIMIT(10)").unwrap(),
+            [
+                Argument::Word("messages".to_string()),
+                Argument::Word("user:1".to_string()),
+                Argument::Text("hello \"world\"".to_string()),
+                Argument::Meta("LIMIT".to_string(), "10".to_string()),
+            ]
+        );
+        assert_eq!(parse_arguments("messages \"hello"), None);
+    }
+
+    fn new_index(test_name_for_isolation: &str) -> sonic_test_index::Index {
+        let config: sonic_test_index::IndexConfig = serde_saphyr::from_str(
+            &std::fs::read_to_string("src/test_sonic_config.yml")
+                .unwrap()
+                .replace("TEST_NAME", test_name_for_isolation),
+        )
+        .unwrap();
+        sonic_test_index::Index::new(config).unwrap()
+    }
+
+    #[test]
+    fn test_server() {
+        let server = SonicServer::new(new_index("test_sonic_server"), "secret");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || server.serve(listener));
+
+        let mut ingest = Client::connect(address, "ingest");
+        assert_eq!(
+            ingest.send("PUSH messages user:1 conversation:1 \"Hello, dear World!\""),
+            "OK"
+        );
+        assert_eq!(
+            ingest.send("PUSH messages user:1 conversation:2 \"hello there\""),
+            "OK"
+        );
+        assert_eq!(
+            ingest.send("PUSH messages user:2 conversation:3 \"hello world\""),
+            "OK"
+        );
+        assert_eq!(
+            ingest.send("PUSH unknown user:1 conversation:1 \"hello\""),
+            "ERR unknown_collection(unknown)"
+        );
+        assert_eq!(
+            ingest.send("PUSH messages user:1"),
+            "ERR invalid_format(PUSH <collection> <bucket> <object> \"<text>\" [LANG(<locale>)]?)"
+        );
+        assert_eq!(
+            ingest.send("QUERY messages user:1 \"hello\""),
+            "ERR unknown_command"
+        );
+        assert_eq!(ingest.send("COUNT messages"), "RESULT 2");
+        assert_eq!(ingest.send("COUNT messages user:1"), "RESULT 2");
+        assert_eq!(
+            ingest.send("COUNT messages user:1 conversation:1"),
+            "RESULT 3"
+        );
+
+        let mut search = Client::connect(address, "search");
+        assert_eq!(search.send("PING"), "PONG");
+        assert_eq!(
+            search.send(&format!(
+                "QUERY messages user:1 \"{}\"",
+                "a".repeat(BUFFER_SIZE)
+            )),
+            "ERR buffer_overflow"
+        );
+        assert_eq!(search.send("PING"), "PONG");
+        assert_eq!(
+            search.send_bytes(b"QUERY messages user:1 \"\xff\""),
+            "ERR invalid_format(command is not UTF-8)"
+        );
+        assert_eq!(search.send("PING"), "PONG");
+        let mut found = search
+            .query("QUERY messages user:1 \"HELLO\"")
+            .split(' ')
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            [
+                search.query("QUERY messages user:1 \"hello\" LIMIT(1)"),
+                search.query("QUERY messages user:1 \"hello\" LIMIT(1) OFFSET(1)")
+            ],
+            found.as_slice()
+        );
+        found.sort();
+        assert_eq!(found, ["conversation:1", "conversation:2"]);
+        assert_eq!(
+            search.query("QUERY messages user:1 \"hello world\""),
+            "conversation:1"
+        );
+        assert_eq!(
+            search.query("QUERY messages user:2 \"hello world\""),
+            "conversation:3"
+        );
+
+        assert_eq!(search.query("SUGGEST messages user:1 \"He\""), "hello");
+        assert_eq!(
+            search.query("SUGGEST messages user:1 \"w\" LIMIT(5)"),
+            "world"
+        );
+        assert_eq!(search.query("SUGGEST messages user:2 \"t\""), "");
+
+        assert_eq!(
+            ingest.send("POP messages user:1 conversation:1 \"world missing\""),
+            "RESULT 1"
+        );
+        assert_eq!(search.query("QUERY messages user:1 \"world\""), "");
+        assert_eq!(
+            ingest.send("FLUSHO messages user:1 conversation:2"),
+            "RESULT 2"
+        );
+        assert_eq!(
+            search.query("QUERY messages user:1 \"hello\""),
+            "conversation:1"
+        );
+        assert_eq!(ingest.send("FLUSHB messages user:1"), "RESULT 1");
+        assert_eq!(ingest.send("COUNT messages"), "RESULT 1");
+        assert_eq!(ingest.send("FLUSHC messages"), "RESULT 1");
+        assert_eq!(search.query("QUERY messages user:2 \"hello\""), "");
+
+        let mut control = Client::connect(address, "control");
+        let info = control.send("INFO");
+        assert!(info.starts_with("RESULT uptime("), "{info}");
+        assert!(
+            info.contains(" clients_connected(3) commands_total(24) "),
+            "{info}"
+        );
+        assert!(!info.contains("kv_open_count"), "{info}");
+        assert_eq!(search.send("QUIT"), "ENDED quit");
+
+        let mut intruder = Client::open(address);
+        assert_eq!(
+            intruder.send("QUERY messages user:1 \"hello\""),
+            "ERR invalid_mode(not_started)"
+        );
+        assert_eq!(
+            intruder.send("START search wrong"),
+            "ENDED authentication_failed"
+        );
+    }
+
+    #[test]
+    fn test_connections_limit() {
+        let server = SonicServer::new(new_index("test_sonic_connections_limit"), "secret")
+            .with_connections_limit(1);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || server.serve(listener));
+
+        let mut first = Client::connect(address, "search");
+        let mut second = TcpStream::connect(address).unwrap();
+        let mut response = String::new();
+        second.read_to_string(&mut response).unwrap();
+        assert_eq!(response, "ENDED connections_limit\r\n");
+        assert_eq!(first.send("PING"), "PONG");
+
+        assert_eq!(first.send("QUIT"), "ENDED quit");
+        let mut third = Client::connect(address, "search");
+        assert_eq!(third.send("PING"), "PONG");
+    }
+}
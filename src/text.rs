@@ -0,0 +1,181 @@
+use std::{collections::BTreeSet, marker::PhantomData};
+
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{Id, IdHasher};
+
+#[rustfmt::skip]
+pub const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "about", "above", "after", "again", "against", "all", "am", "an", "and", "any", "are",
+    "as", "at", "be", "because", "been", "before", "being", "below", "between", "both", "but", "by",
+    "can", "did", "do", "does", "doing", "down", "during", "each", "few", "for", "from", "further",
+    "had", "has", "have", "having", "he", "her", "here", "hers", "herself", "him", "himself", "his",
+    "how", "i", "if", "in", "into", "is", "it", "its", "itself", "just", "me", "more", "most", "my",
+    "myself", "no", "nor", "not", "now", "of", "off", "on", "once", "only", "or", "other", "our",
+    "ours", "ourselves", "out", "over", "own", "same", "she", "should", "so", "some", "such",
+    "than", "that", "the", "their", "theirs", "them", "themselves", "then", "there", "these",
+    "they", "this", "those", "through", "to", "too", "under", "until", "up", "very", "was", "we",
+    "were", "what", "when", "where", "which", "while", "who", "whom", "why", "will", "with", "you",
+    "your", "yours", "yourself", "yourselves",
+];
+
+/// Turns text into word tags: Unicode word segmentation, lowercasing, stop words removal and optional stemming;
+/// use the same tagger for inserted texts and search queries, so their tags agree
+pub struct TextTagger<H: IdHasher> {
+    prefix: String,
+    stop_words: BTreeSet<String>,
+    #[cfg(feature = "stemming")]
+    stemmer: Option<rust_stemmers::Stemmer>,
+    hasher: PhantomData<H>,
+}
+
+impl<H: IdHasher> Default for TextTagger<H> {
+    fn default() -> Self {
+        Self {
+            prefix: "word:".to_string(),
+            stop_words: BTreeSet::new(),
+            #[cfg(feature = "stemming")]
+            stemmer: None,
+            hasher: PhantomData,
+        }
+    }
+}
+
+impl<H: IdHasher> TextTagger<H> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// prepended to every word before hashing, `word:` by default, keeps word tags apart from other tags
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    pub fn with_stop_words<'a, I: IntoIterator<Item = &'a str>>(mut self, stop_words: I) -> Self {
+        self.stop_words
+            .extend(stop_words.into_iter().map(str::to_lowercase));
+        self
+    }
+
+    #[cfg(feature = "stemming")]
+    pub fn with_stemmer(mut self, algorithm: rust_stemmers::Algorithm) -> Self {
+        self.stemmer = Some(rust_stemmers::Stemmer::create(algorithm));
+        self
+    }
+
+    /// normalized words in order of appearance, possibly repeated
+    pub fn words(&self, text: &str) -> Vec<String> {
+        text.unicode_words()
+            .map(str::to_lowercase)
+            .filter(|word| !self.stop_words.contains(word))
+            .map(|word| self.stem(word))
+            .collect()
+    }
+
+    fn stem(&self, word: String) -> String {
+        #[cfg(feature = "stemming")]
+        if let Some(stemmer) = &self.stemmer {
+            return stemmer.stem(&word).into_owned();
+        }
+        word
+    }
+
+    pub fn tag(&self, word: &str) -> Id {
+        Id::from_str_hash::<H>(&format!("{}{word}", self.prefix))
+    }
+
+    /// sorted and deduplicated, ready to be inserted or used as present tags
+    pub fn tags(&self, text: &str) -> Vec<Id> {
+        self.named_tags(text)
+            .into_iter()
+            .map(|(_, tag)| tag)
+            .collect()
+    }
+
+    /// same as `tags`, paired with their words, for `<schema>_insert_named`
+    pub fn named_tags(&self, text: &str) -> Vec<(String, Id)> {
+        let mut result = self
+            .words(text)
+            .into_iter()
+            .map(|word| {
+                let tag = self.tag(&word);
+                (word, tag)
+            })
+            .collect::<Vec<_>>();
+        result.sort_by(|(_, left_tag), (_, right_tag)| left_tag.cmp(right_tag));
+        result.dedup_by(|(_, left_tag), (_, right_tag)| left_tag == right_tag);
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    struct FirstBytes;
+
+    impl IdHasher for FirstBytes {
+        fn hash(bytes: &[u8]) -> [u8; 16] {
+            let mut result = [0u8; 16];
+            for (target, source) in result.iter_mut().zip(bytes) {
+                *target = *source;
+            }
+            result
+        }
+    }
+
+    #[test]
+    fn test_words() {
+        let tagger =
+            TextTagger::<FirstBytes>::new().with_stop_words(ENGLISH_STOP_WORDS.iter().copied());
+        assert_eq!(
+            tagger.words("The QUICK brown fox, the lazy dog's Ünïcödé — 42 times!"),
+            [
+                "quick",
+                "brown",
+                "fox",
+                "lazy",
+                "dog's",
+                "ünïcödé",
+                "42",
+                "times"
+            ]
+        );
+        assert_eq!(tagger.words("to be or not to be"), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_tags() {
+        let tagger = TextTagger::<FirstBytes>::new().with_prefix("w:");
+        assert_eq!(
+            tagger.tags("b a B"),
+            [
+                Id::from_str_hash::<FirstBytes>("w:a"),
+                Id::from_str_hash::<FirstBytes>("w:b")
+            ]
+        );
+        assert_eq!(tagger.tags("A"), [tagger.tag("a")]);
+        assert_eq!(
+            tagger.named_tags("b a B"),
+            [
+                ("a".to_string(), tagger.tag("a")),
+                ("b".to_string(), tagger.tag("b"))
+            ]
+        );
+    }
+
+    #[cfg(feature = "stemming")]
+    #[test]
+    fn test_stemming() {
+        let tagger =
+            TextTagger::<FirstBytes>::new().with_stemmer(rust_stemmers::Algorithm::English);
+        assert_eq!(
+            tagger.words("Running runners ran"),
+            ["run", "runner", "ran"]
+        );
+        assert_eq!(tagger.tags("runs"), tagger.tags("running"));
+    }
+}
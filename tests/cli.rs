@@ -0,0 +1,187 @@
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+use pretty_assertions::assert_eq;
+
+extern crate dream;
+use dream::*;
+
+define_index!(cli_test_index(
+    public
+) {
+} use {
+});
+
+const O1: &str = "15151515151515151515151515151515";
+const O2: &str = "16161616161616161616161616161616";
+const O3: &str = "17171717171717171717171717171717";
+const T1: &str = "0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b0b";
+const T2: &str = "0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c0c";
+
+fn new_config(test_name_for_isolation: &str) -> PathBuf {
+    let directory = Path::new("/tmp/dream/test").join(test_name_for_isolation);
+    let _ = std::fs::remove_dir_all(&directory);
+    std::fs::create_dir_all(&directory).unwrap();
+    let config = directory.join("config.yml");
+    std::fs::write(
+        &config,
+        std::fs::read_to_string("src/test_index_config.yml")
+            .unwrap()
+            .replace("TEST_NAME", test_name_for_isolation),
+    )
+    .unwrap();
+    config
+}
+
+fn run(config: &Path, arguments: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_dream"))
+        .arg(config)
+        .args(arguments)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn open_index(config: &Path) -> cli_test_index::Index {
+    cli_test_index::Index::new(
+        serde_saphyr::from_str(&std::fs::read_to_string(config).unwrap()).unwrap(),
+    )
+    .unwrap()
+}
+
+fn lines(ids: &[&str]) -> String {
+    ids.iter().map(|id| format!("{id}\n")).collect()
+}
+
+#[test]
+fn test_cli() {
+    let config = new_config("test_cli");
+
+    stdout(&run(&config, &["insert", O1, T1]));
+    stdout(&run(&config, &["insert", O2, T1, T2]));
+    stdout(&run(&config, &["insert", O3, T2]));
+
+    assert_eq!(
+        stdout(&run(&config, &["search", "--present", T1])),
+        lines(&[O1, O2])
+    );
+    assert_eq!(
+        stdout(&run(&config, &["search", "--absent", T1, "--descending"])),
+        lines(&[O3])
+    );
+    assert_eq!(stdout(&run(&config, &["get-tags", O2])), lines(&[T1, T2]));
+
+    let first_page = run(&config, &["search", "--present", T2, "--limit", "1"]);
+    assert_eq!(stdout(&first_page), lines(&[O2]));
+    let token = String::from_utf8(first_page.stderr).unwrap();
+    let token = token.trim();
+    assert_eq!(
+        stdout(&run(&config, &["search", "--after", token])),
+        lines(&[O3])
+    );
+
+    for arguments in [
+        &["search", "--after", token, "--present", T1][..],
+        &["search", "--absent", T1, "--after", token],
+        &["search", "--after", token, "--descending"],
+        &["search", "--limit"],
+        &["search", "--present", "not an identifier"],
+        &["get-tags"],
+        &["unknown"],
+    ] {
+        let output = run(&config, arguments);
+        assert!(!output.status.success(), "{arguments:?}");
+        assert!(output.stdout.is_empty(), "{arguments:?}");
+    }
+    assert!(
+        String::from_utf8(run(&config, &["search", "--after", token, "--present", T1]).stderr)
+            .unwrap()
+            .contains("can not be combined")
+    );
+}
+
+#[test]
+fn test_cli_remove_and_stats() {
+    let config = new_config("test_cli_remove_and_stats");
+
+    stdout(&run(&config, &["insert", O1, T1]));
+    stdout(&run(&config, &["insert", O2, T1, T2]));
+    stdout(&run(&config, &["insert", O3, T2]));
+    assert_eq!(
+        stdout(&run(&config, &["stats", T1, T2])),
+        format!("pairs: 4\ntags: 2\nobjects: 3\n{T1}: 2\n{T2}: 2\n")
+    );
+
+    stdout(&run(&config, &["remove", O2, T2]));
+    assert_eq!(stdout(&run(&config, &["get-tags", O2])), lines(&[T1]));
+    assert_eq!(
+        stdout(&run(&config, &["search", "--present", T2])),
+        lines(&[O3])
+    );
+
+    stdout(&run(&config, &["remove", O1]));
+    assert_eq!(stdout(&run(&config, &["get-tags", O1])), "");
+    assert_eq!(
+        stdout(&run(&config, &["search", "--present", T1])),
+        lines(&[O2])
+    );
+    assert_eq!(
+        stdout(&run(&config, &["stats", T1, T2])),
+        format!("pairs: 2\ntags: 2\nobjects: 2\n{T1}: 1\n{T2}: 1\n")
+    );
+
+    for arguments in [&["remove"][..], &["remove", O1, "not an identifier"]] {
+        let output = run(&config, arguments);
+        assert!(!output.status.success(), "{arguments:?}");
+    }
+}
+
+#[test]
+fn test_cli_verify() {
+    let config = new_config("test_cli_verify");
+
+    stdout(&run(&config, &["insert", O1, T1]));
+    let consistent = "pairs only in tag_and_object: 0\n\
+                      pairs only in object_and_tag: 0\n\
+                      untagged objects: 0\n\
+                      missing objects: 0\n";
+    assert_eq!(stdout(&run(&config, &["verify"])), consistent);
+
+    let mut index = open_index(&config);
+    index
+        .lock_all_and_write(|transaction| {
+            let untagged = O2.parse::<Id>()?;
+            transaction
+                .database_transaction
+                .public
+                .object
+                .insert(untagged, ());
+            Ok(())
+        })
+        .unwrap();
+    drop(index);
+
+    let inconsistent = format!(
+        "pairs only in tag_and_object: 0\n\
+         pairs only in object_and_tag: 0\n\
+         untagged objects: 1\n    {O2}\n\
+         missing objects: 0\n"
+    );
+    let output = run(&config, &["verify"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), inconsistent);
+
+    assert_eq!(stdout(&run(&config, &["verify", "--repair"])), inconsistent);
+    assert_eq!(stdout(&run(&config, &["verify"])), consistent);
+    assert!(!run(&config, &["verify", "--unknown"]).status.success());
+}